@@ -1,7 +1,13 @@
 #![allow(dead_code)]
 
+use bevy::asset::AssetLoader;
+use bevy::asset::LoadContext;
+use bevy::asset::LoadState;
+use bevy::asset::io::Reader;
 use bevy::color::palettes::css::{BLUE, GREEN, RED};
 use bevy::prelude::*;
+use serde::de::DeserializeOwned;
+use std::marker::PhantomData;
 
 #[derive(Resource)]
 pub struct BasicColorHandles {
@@ -20,3 +26,57 @@ impl BasicColorHandles {
         }
     }
 }
+
+/// Assets that have to be loaded before leaving the splash screen.
+#[derive(Resource, Default)]
+pub struct PreloadedAssets(pub Vec<UntypedHandle>);
+impl PreloadedAssets {
+    /// Whether all assets are loaded. Fails as soon as one of them failed to load, so that the
+    /// game does not wait for it forever.
+    pub fn all_loaded(&self, asset_server: &AssetServer) -> Result<bool> {
+        for handle in &self.0 {
+            if let Some(LoadState::Failed(error)) = asset_server.get_load_state(handle.id()) {
+                return Err(format!("Preloaded asset failed to load: {error}").into());
+            }
+        }
+        Ok(self
+            .0
+            .iter()
+            .all(|handle| asset_server.is_loaded_with_dependencies(handle.id())))
+    }
+}
+
+/// Loads any deserializable asset from a RON file with one of the given extensions.
+#[derive(TypePath)]
+pub struct RonLoader<A> {
+    extensions: &'static [&'static str],
+    _marker: PhantomData<fn() -> A>,
+}
+impl<A> RonLoader<A> {
+    pub fn new(extensions: &'static [&'static str]) -> Self {
+        Self {
+            extensions,
+            _marker: PhantomData,
+        }
+    }
+}
+impl<A: Asset + DeserializeOwned> AssetLoader for RonLoader<A> {
+    type Asset = A;
+    type Settings = ();
+    type Error = BevyError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<A, BevyError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        self.extensions
+    }
+}
@@ -1,43 +1,85 @@
+mod ai;
+mod ballistics;
+mod boss;
+mod bullets;
+mod champion;
+mod damage;
+mod director;
+mod elite;
 mod enemy;
+mod explosion;
+mod hazard;
 mod hud;
 mod items;
+mod nav;
 mod pause;
+mod platformer;
 mod player;
+mod skills;
 mod stages;
+mod stats;
+mod status;
+mod summons;
+
+pub use champion::SelectedChampion;
 
 use crate::Cursor;
 use crate::METERS_PER_PIXEL;
 use crate::MainState;
 use crate::PIXELS_PER_METER;
+use crate::assets::RonLoader;
 use crate::utils::Lifetime;
+use crate::utils::Rng;
 
 use avian2d::prelude::*;
 use bevy::input::common_conditions::input_just_pressed;
 use bevy::prelude::*;
 use bevy::time::Stopwatch;
+use serde::Deserialize;
 use std::collections::HashMap;
 
 const SPRITE_ORIENTATION: Vec2 = Vec2::Y;
-const ENEMY_SPAWN_INTERVAL: f32 = 5.0;
 /// Rate of exponential decay in the distance between camera and its goal.
 const CAMERA_SPEED: f32 = 8.0;
 const CURSOR_CAMERA_INFLUENCE: f32 = 0.3;
 const ATTACK_SPEED: f32 = 2.0;
 const PLAYER_SPEED: f32 = 3.0;
 const PLAYER_MAX_HEALTH: f32 = 100.0;
+const CRIT_CHANCE: f32 = 0.05;
+const CRIT_MULTIPLIER: f32 = 2.0;
+const SHIELD_RECHARGE_DELAY: f32 = 3.0;
+const SHIELD_RECHARGE_RATE: f32 = 10.0;
 
 pub fn game_plugin(app: &mut App) {
     app.add_plugins(PhysicsPlugins::default().with_length_unit(PIXELS_PER_METER))
         .insert_resource(Gravity(-10.0 * Vec2::Y))
+        .init_asset::<champion::ChampionDefinition>()
+        .register_asset_loader(RonLoader::<champion::ChampionDefinition>::new(&[
+            "champion.ron",
+        ]))
+        .init_asset::<elite::AffixTable>()
+        .register_asset_loader(RonLoader::<elite::AffixTable>::new(&["affixes.ron"]))
+        .init_resource::<SelectedChampion>()
+        .init_resource::<RunSeed>()
+        .init_resource::<nav::NavGraph>()
+        .add_systems(Startup, (champion::load, elite::load))
         .add_sub_state::<GameSubState>()
         .add_message::<PlayerDeath>()
-        .add_message::<PlayerDamage>()
+        .add_message::<damage::Hit>()
         .add_message::<ItemPickup>()
         .add_message::<ChangeStage>()
+        .add_message::<skills::SkillUsed>()
+        .add_message::<explosion::Explosion>()
+        .add_message::<status::Inflict>()
+        .add_message::<boss::BossDefeated>()
         .clear_messages_on_exit::<PlayerDeath>(MainState::Game)
-        .clear_messages_on_exit::<PlayerDamage>(MainState::Game)
+        .clear_messages_on_exit::<damage::Hit>(MainState::Game)
         .clear_messages_on_exit::<ItemPickup>(MainState::Game)
         .clear_messages_on_exit::<ChangeStage>(MainState::Game)
+        .clear_messages_on_exit::<skills::SkillUsed>(MainState::Game)
+        .clear_messages_on_exit::<explosion::Explosion>(MainState::Game)
+        .clear_messages_on_exit::<status::Inflict>(MainState::Game)
+        .clear_messages_on_exit::<boss::BossDefeated>(MainState::Game)
         .add_systems(
             OnEnter(MainState::Game),
             (
@@ -51,6 +93,12 @@ pub fn game_plugin(app: &mut App) {
         // Only game state changes the camera position so resetting camera when exiting it
         // should solve all with camera position.
         .add_systems(OnExit(MainState::Game), reset_camera)
+        .add_systems(
+            PostUpdate,
+            nav::build
+                .after(TransformSystems::Propagate)
+                .run_if(in_state(MainState::Game)),
+        )
         .add_systems(
             RunFixedMainLoop,
             update_camera_and_cursor
@@ -62,13 +110,48 @@ pub fn game_plugin(app: &mut App) {
             (
                 // TODO Consider the order of these systems, having also in mind when Avian physics runs.
                 player::hit,
-                player::take_damage,
+                player::check_death,
                 player::update_looking,
-                player::attack,
                 player::handle_input,
-                enemy::hit,
-                enemy::handle_state,
-                Health::system,
+                (platformer::update_grounded, platformer::jump).chain(),
+                // Systems drawing from the damage stream of `RunRng` run in a fixed order.
+                (
+                    skills::trigger,
+                    (skills::rifle, skills::shotgun, skills::grenade).chain(),
+                    skills::target_lock,
+                    skills::expire_marks,
+                    skills::dash,
+                    skills::end_dash,
+                    summons::summon,
+                ),
+                (ballistics::home, ballistics::ricochet, enemy::hit),
+                explosion::detonate,
+                explosion::explode,
+                (director::direct, elite::roll).chain(),
+                (ai::think, nav::follow, ai::act).chain(),
+                // Projectiles must hurt the player and summons before they are despawned.
+                (
+                    bullets::emit,
+                    bullets::impact.after(player::hit).after(summons::hit),
+                ),
+                (boss::enter_room, boss::act),
+                elite::trail,
+                (summons::handle_state.after(skills::grenade), summons::hit),
+                (hazard::cycle, hazard::damage).chain(),
+                (status::inflict, status::tick, stats::recompute).chain(),
+                (
+                    damage::apply_hits,
+                    damage::regenerate,
+                    damage::end_hitstun,
+                    damage::end_invulnerability,
+                ),
+                // Deaths are handled after this tick's hits, before the dead are despawned.
+                (
+                    boss::defeat.after(damage::apply_hits),
+                    elite::on_death,
+                    Health::system,
+                )
+                    .chain(),
                 Lifetime::system,
             )
                 .run_if(in_state(MainState::Game)),
@@ -76,24 +159,45 @@ pub fn game_plugin(app: &mut App) {
         .add_systems(
             Update,
             (
-                enemy::spawn,
-                stages::stage1.run_if(on_message::<ChangeStage>),
+                stages::stage1.run_if(stages::changing_to(1)),
+                stages::stage2.run_if(stages::changing_to(2)),
                 pause::toggle.run_if(input_just_pressed(KeyCode::Escape)),
                 player::visual_state,
+                damage::blink,
                 update_run,
                 exit_game
                     .run_if(input_just_pressed(KeyCode::F4).or_else(on_message::<PlayerDeath>)),
-                player::update_stats.run_if(on_message::<ItemPickup>),
+                player::update_projectile_modifiers.run_if(on_message::<ItemPickup>),
                 stages::door_interaction.run_if(input_just_pressed(KeyCode::KeyE)),
                 items::pickup.run_if(input_just_pressed(KeyCode::KeyE)),
-                hud::update_run_time,
-                hud::update_health,
-                hud::update_equipment.run_if(on_message::<ItemPickup>),
+                hazard::toggle_switches.run_if(input_just_pressed(KeyCode::KeyE)),
+                hazard::visual_state,
+                enemy::tint,
+                ai::telegraph,
+                (
+                    boss::unseal_doors.run_if(on_message::<boss::BossDefeated>),
+                    boss::drop_loot.run_if(on_message::<boss::BossDefeated>),
+                ),
+                (
+                    hud::update_run_time,
+                    hud::update_health,
+                    hud::update_cooldowns,
+                    hud::update_statuses,
+                    hud::update_equipment.run_if(on_message::<ItemPickup>),
+                    hud::update_boss,
+                ),
             )
                 .run_if(in_state(MainState::Game)),
         );
 }
-fn run_start(mut commands: Commands) {
+fn run_start(mut commands: Commands, run_seed: Res<RunSeed>) {
+    let seed = run_seed.0.unwrap_or_else(|| {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_nanos() as u64)
+    });
+    info!("Run seed: {seed}");
+    commands.insert_resource(RunRng::new(seed));
     commands.spawn((
         Name::new("Run"),
         Run::default(),
@@ -161,21 +265,8 @@ struct ItemPickup;
 #[derive(Message, Default)]
 struct PlayerDeath;
 #[derive(Message)]
-struct PlayerDamage(f32);
-#[allow(dead_code)]
-#[derive(Message)]
 struct ChangeStage(u8);
 
-#[derive(Component)]
-struct EnemySpawner(Timer);
-impl Default for EnemySpawner {
-    fn default() -> Self {
-        EnemySpawner(Timer::from_seconds(
-            ENEMY_SPAWN_INTERVAL,
-            TimerMode::Repeating,
-        ))
-    }
-}
 #[derive(Component)]
 struct Health(f32);
 impl Health {
@@ -189,30 +280,86 @@ impl Health {
     }
 }
 #[derive(Component)]
-struct Projectile;
+struct Projectile {
+    /// Entity that shot the projectile.
+    source: Entity,
+    damage: damage::Damage,
+    /// Entities already hit, which the projectile passes through.
+    hit: Vec<Entity>,
+}
+impl Projectile {
+    fn new(source: Entity, damage: damage::Damage) -> Self {
+        Self {
+            source,
+            damage,
+            hit: Vec::new(),
+        }
+    }
+}
 #[derive(Component)]
 struct Player;
 #[derive(Component)]
 struct Enemy;
-// TODO Is Health part of Stats? Maybe create a special PlayerStats to extend on Health
-#[derive(Component)]
+/// Maxima and rates of the player.
+/// Current values, like `Health` or `damage::Shield`, live in their own components.
+#[derive(Component, Clone, Deserialize)]
+#[serde(default)]
 struct Stats {
     max_health: f32,
+    /// Scales cooldowns of attacks.
     attack_speed: f32,
-    _movement_speed: f32,
+    /// Scales cooldowns of abilities, which pass `1 + ability_haste` times faster.
+    ability_haste: f32,
+    /// Maximum horizontal speed in meters per second.
+    movement_speed: f32,
+    /// Scales the damage of all skills.
+    base_damage: f32,
+    /// Probability of a hit being critical.
+    crit_chance: f32,
+    /// Damage multiplier of critical hits.
+    crit_multiplier: f32,
+    /// Maximum shield, which absorbs damage before health.
+    shield: f32,
+    /// Seconds without taking damage before the shield starts recharging.
+    shield_recharge_delay: f32,
+    /// Shield recharged per second.
+    shield_recharge_rate: f32,
+    /// Reduces every hit to `100 / (100 + armor)` of its damage.
+    armor: f32,
+    /// Health regenerated per second.
+    health_regen: f32,
 }
 impl Default for Stats {
     fn default() -> Self {
         Self {
             max_health: PLAYER_MAX_HEALTH,
             attack_speed: ATTACK_SPEED,
-            _movement_speed: PLAYER_SPEED,
+            ability_haste: 0.0,
+            movement_speed: PLAYER_SPEED,
+            base_damage: 1.0,
+            crit_chance: CRIT_CHANCE,
+            crit_multiplier: CRIT_MULTIPLIER,
+            shield: 0.0,
+            shield_recharge_delay: SHIELD_RECHARGE_DELAY,
+            shield_recharge_rate: SHIELD_RECHARGE_RATE,
+            armor: 0.0,
+            health_regen: 0.0,
         }
     }
 }
 impl Stats {
-    fn apply_equipment(&mut self, eq: &Equipment) {
-        self.attack_speed *= 1.0 + eq.item_stat(&Item::Banana);
+    /// Damage left of a hit after the armor.
+    fn mitigate(&self, amount: f32) -> f32 {
+        amount * 100.0 / (100.0 + self.armor.max(0.0))
+    }
+    /// Damage of a skill scaled by `base_damage`, critical with the `crit_chance`.
+    fn roll_damage(&self, amount: f32, kind: damage::DamageType, rng: &mut Rng) -> damage::Damage {
+        let damage = damage::Damage::new(self.base_damage * amount, kind);
+        if rng.chance(self.crit_chance) {
+            damage.critical(self.crit_multiplier)
+        } else {
+            damage
+        }
     }
 }
 
@@ -236,28 +383,86 @@ impl Equipment {
     fn item_stat(&self, item: &Item) -> f32 {
         *self.0.get(item).unwrap_or(&0u8) as f32 * Item::stat(item)
     }
+    /// One modifier per item that changes `Stats`.
+    fn stat_modifiers(&self) -> impl Iterator<Item = stats::StatModifier> + '_ {
+        self.0.iter().flat_map(|(item, count)| {
+            item.stat_modifier()
+                .into_iter()
+                .flat_map(move |modifier| std::iter::repeat_n(modifier, *count as usize))
+        })
+    }
+    fn projectile_modifiers(&self) -> ballistics::ProjectileModifiers {
+        ballistics::ProjectileModifiers {
+            ricochet: self.item_stat(&Item::Spring) as u32,
+            homing: self.item_stat(&Item::Magnet),
+            ..default()
+        }
+    }
 }
 
 #[derive(Component, PartialEq, Eq, Hash, Clone)]
 enum Item {
     Banana,
+    Coffee,
+    Dice,
+    Spring,
+    Magnet,
 }
 impl Item {
+    const ALL: [Item; 5] = [
+        Self::Banana,
+        Self::Coffee,
+        Self::Dice,
+        Self::Spring,
+        Self::Magnet,
+    ];
+
     // TODO Knowing the pixel (and meter) size should not be runtime.
     fn size(&self) -> Vec2 {
         match self {
-            Self::Banana => METERS_PER_PIXEL * Vec2::new(16.0, 16.0),
+            Self::Banana | Self::Coffee | Self::Dice | Self::Spring | Self::Magnet => {
+                METERS_PER_PIXEL * Vec2::new(16.0, 16.0)
+            }
         }
     }
     fn image(&self, asset_server: &Res<AssetServer>) -> Handle<Image> {
         match self {
             Self::Banana => asset_server.load("banana.png"),
+            Self::Coffee => asset_server.load("coffee.png"),
+            Self::Dice => asset_server.load("dice.png"),
+            Self::Spring => asset_server.load("spring.png"),
+            Self::Magnet => asset_server.load("magnet.png"),
         }
     }
     fn stat(&self) -> f32 {
         match self {
             // Attack speed
             Self::Banana => 0.5,
+            // Ability haste
+            Self::Coffee => 0.2,
+            // Crit chance
+            Self::Dice => 0.1,
+            // Projectile ricochets
+            Self::Spring => 1.0,
+            // Projectile homing turn rate
+            Self::Magnet => 2.0,
+        }
+    }
+    fn stat_modifier(&self) -> Option<stats::StatModifier> {
+        match self {
+            Self::Banana => Some(stats::StatModifier::AdditivePercent(
+                stats::Stat::AttackSpeed,
+                self.stat(),
+            )),
+            Self::Coffee => Some(stats::StatModifier::Flat(
+                stats::Stat::AbilityHaste,
+                self.stat(),
+            )),
+            Self::Dice => Some(stats::StatModifier::Flat(
+                stats::Stat::CritChance,
+                self.stat(),
+            )),
+            Self::Spring | Self::Magnet => None,
         }
     }
 }
@@ -265,6 +470,45 @@ impl Item {
 #[derive(Component, Default)]
 struct Run(Stopwatch);
 
+/// Seed of the random number generator of the next run, random if there is none.
+/// Taken from the `SEED` environment variable, so that a run can be replayed.
+#[derive(Resource)]
+struct RunSeed(Option<u64>);
+impl Default for RunSeed {
+    fn default() -> Self {
+        RunSeed(
+            std::env::var("SEED")
+                .ok()
+                .and_then(|seed| seed.parse().ok()),
+        )
+    }
+}
+
+/// Random streams of a run, all derived from its seed. Each stream is drawn from only in
+/// `FixedUpdate` and in a fixed order, so that a seed reproduces the run.
+#[derive(Resource)]
+struct RunRng {
+    /// Critical hits of the player and its summons.
+    damage: Rng,
+    /// Enemies spawned by directors.
+    director: Rng,
+    /// Affixes of elites.
+    elite: Rng,
+    /// Items dropped by enemies.
+    loot: Rng,
+}
+impl RunRng {
+    fn new(seed: u64) -> Self {
+        let mut seeds = Rng::seeded(seed);
+        RunRng {
+            damage: Rng::seeded(seeds.next_u64()),
+            director: Rng::seeded(seeds.next_u64()),
+            elite: Rng::seeded(seeds.next_u64()),
+            loot: Rng::seeded(seeds.next_u64()),
+        }
+    }
+}
+
 #[derive(Default, PhysicsLayer)]
 enum CollisionGroup {
     #[default]
@@ -273,4 +517,9 @@ enum CollisionGroup {
     Player,
     Projectile,
     Enemy,
+    /// Allies of the player, which do not collide with the player.
+    Summon,
+    Hazard,
+    /// Projectiles of enemies, which hit the player, summons and terrain, but not enemies.
+    EnemyProjectile,
 }
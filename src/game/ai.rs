@@ -0,0 +1,393 @@
+use super::CollisionGroup;
+use super::Enemy;
+use super::Health;
+use super::Player;
+use super::SPRITE_ORIENTATION;
+use super::bullets::BulletPattern;
+use super::bullets::fire;
+use super::damage::Damage;
+use super::damage::DamageType;
+use super::damage::Hitstun;
+use super::elite::Elite;
+use super::enemy::Archetype;
+use super::enemy::MaxHealth;
+use super::nav::NavPath;
+use super::status::StatusEffects;
+
+use avian2d::prelude::*;
+use bevy::prelude::*;
+
+/// Ranged enemies flee when the player is closer than this.
+const RANGED_MIN_DISTANCE: f32 = 4.0;
+/// Fleeing ranged enemies turn around once the player is further than this.
+const RANGED_SAFE_DISTANCE: f32 = 6.0;
+/// Enemies forget the player once it is this much further than their sight range.
+const LOSE_SIGHT_FACTOR: f32 = 1.5;
+/// Seconds without line of sight after which enemies forget the player.
+const LOSE_SIGHT_TIME: f32 = 2.0;
+const IDLE_TIME: f32 = 1.5;
+const PATROL_TIME: f32 = 3.0;
+/// Distance from the spawn point within which enemies patrol.
+const PATROL_RANGE: f32 = 3.0;
+/// Fraction of their speed enemies patrol with.
+const PATROL_SPEED: f32 = 0.5;
+/// Pause between noticing the player and chasing it.
+const NOTICE_TIME: f32 = 0.4;
+const FLEE_TIME: f32 = 2.0;
+const RANGED_SHOT_SPEED: f32 = 6.0;
+const RANGED_SHOT_DAMAGE: f32 = 8.0;
+const TELEGRAPH_COLOR: Color = Color::srgb(1.0, 0.1, 0.1);
+const TELEGRAPH_SIZE: Vec2 = Vec2::new(0.1, 0.35);
+
+/// Moves enemies to their next state, based on the distance to the player, line of sight and
+/// the timer of their current state.
+pub fn think(
+    time_fixed: Res<Time>,
+    spatial_query: SpatialQuery,
+    mut q_enemies: Query<
+        (
+            &Archetype,
+            &GlobalTransform,
+            &Health,
+            &MaxHealth,
+            &StatusEffects,
+            &mut Behaviour,
+        ),
+        With<Enemy>,
+    >,
+    q_player: Query<&GlobalTransform, (With<Player>, Without<Enemy>)>,
+) -> Result {
+    let player_pos = q_player.single()?.translation().xy();
+    let filter = SpatialQueryFilter::from_mask(CollisionGroup::Terrain);
+    let dt = time_fixed.delta();
+    q_enemies.iter_mut().for_each(
+        |(archetype, transform, health, max_health, effects, mut behaviour)| {
+            if effects.stunned() {
+                return;
+            }
+            let position = transform.translation().xy();
+            let offset = player_pos - position;
+            let line_of_sight = Dir2::new(offset).is_ok_and(|direction| {
+                spatial_query
+                    .cast_ray(position, direction, offset.length(), true, &filter)
+                    .is_none()
+            });
+            if line_of_sight {
+                behaviour.unseen.reset();
+            } else {
+                behaviour.unseen.tick(dt);
+            }
+            behaviour.timer.tick(dt);
+
+            let senses = Senses {
+                offset,
+                line_of_sight,
+                lost: offset.length() > LOSE_SIGHT_FACTOR * archetype.sight_range()
+                    || behaviour.unseen.is_finished(),
+                health_fraction: health.0 / max_health.0,
+            };
+            if let Some(state) = behaviour.next(archetype, &senses) {
+                if state == AiState::Attack {
+                    behaviour.aim = offset.normalize_or_zero();
+                }
+                if state == AiState::Flee {
+                    behaviour.fled = true;
+                }
+                behaviour.enter(state, archetype);
+            }
+        },
+    );
+
+    Ok(())
+}
+/// Moves enemies according to their state, and performs their attacks.
+pub fn act(
+    mut commands: Commands,
+    mut q_enemies: Query<
+        (
+            &Archetype,
+            &GlobalTransform,
+            &mut Transform,
+            &mut LinearVelocity,
+            &StatusEffects,
+            &mut Behaviour,
+            Option<&NavPath>,
+            Option<&Elite>,
+        ),
+        (With<Enemy>, Without<Hitstun>),
+    >,
+    q_player: Query<&GlobalTransform, (With<Player>, Without<Enemy>)>,
+) -> Result {
+    let player_pos = q_player.single()?.translation().xy();
+    q_enemies.iter_mut().for_each(
+        |(
+            archetype,
+            global_transform,
+            mut transform,
+            mut v,
+            effects,
+            mut behaviour,
+            path,
+            elite,
+        )| {
+            let position = global_transform.translation().xy();
+            let towards_player = (player_pos - position).normalize_or_zero();
+            let speed = archetype.speed()
+                * effects.movement_factor()
+                * elite.map_or(1.0, |elite| elite.speed);
+            let direction = match behaviour.state {
+                AiState::Idle | AiState::Notice | AiState::Windup | AiState::Recover => Vec2::ZERO,
+                AiState::Patrol => {
+                    let from_home = position.x - behaviour.home.x;
+                    if from_home.abs() > PATROL_RANGE
+                        && from_home.signum() == behaviour.patrol_direction
+                    {
+                        behaviour.patrol_direction = -behaviour.patrol_direction;
+                    }
+                    PATROL_SPEED * behaviour.patrol_direction * Vec2::X
+                }
+                // Ground enemies follow their path, until they are on the player's node.
+                AiState::Chase => path
+                    .and_then(|path| path.direction(position))
+                    .unwrap_or(towards_player),
+                AiState::Flee => -towards_player,
+                AiState::Attack => {
+                    if *archetype == Archetype::Ranged {
+                        if !behaviour.attacked {
+                            fire(
+                                &mut commands,
+                                BulletPattern::Aimed,
+                                0,
+                                global_transform.translation(),
+                                behaviour.aim,
+                                RANGED_SHOT_SPEED,
+                                Damage::new(RANGED_SHOT_DAMAGE, DamageType::Piercing)
+                                    .scaled(elite.map_or(1.0, |elite| elite.damage)),
+                            );
+                            behaviour.attacked = true;
+                        }
+                        Vec2::ZERO
+                    } else {
+                        archetype.lunge_speed() / archetype.speed() * behaviour.aim
+                    }
+                }
+            };
+
+            if archetype.flying() {
+                if towards_player != Vec2::ZERO {
+                    transform.rotation =
+                        Quat::from_rotation_arc_2d(SPRITE_ORIENTATION, towards_player);
+                }
+                v.0 = speed * direction;
+            } else {
+                // Ground enemies move at full speed even toward a player above them, but
+                // `signum` of zero would still walk right.
+                v.x = if direction.x == 0.0 {
+                    0.0
+                } else {
+                    speed * direction.x.signum() * direction.length()
+                };
+            }
+        },
+    );
+
+    Ok(())
+}
+/// Shows the telegraph of enemies winding up an attack.
+pub fn telegraph(
+    q_enemies: Query<(&Behaviour, &Children)>,
+    mut q_telegraphs: Query<&mut Visibility, With<Telegraph>>,
+) {
+    q_enemies.iter().for_each(|(behaviour, children)| {
+        let mut iter = q_telegraphs.iter_many_mut(children);
+        while let Some(mut visibility) = iter.fetch_next() {
+            *visibility = match behaviour.state {
+                AiState::Windup => Visibility::Inherited,
+                _ => Visibility::Hidden,
+            };
+        }
+    });
+}
+
+/// Marker above an enemy, visible while it winds up an attack.
+pub fn telegraph_marker(size: Vec2) -> impl Bundle {
+    (
+        Telegraph,
+        Sprite::from_color(TELEGRAPH_COLOR, TELEGRAPH_SIZE),
+        Transform::from_translation(Vec3::new(0.0, size.y / 2.0 + TELEGRAPH_SIZE.y, 0.1)),
+        Visibility::Hidden,
+    )
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AiState {
+    /// Stands still.
+    Idle,
+    /// Walks back and forth around its home.
+    Patrol,
+    /// Has just seen the player.
+    Notice,
+    /// Follows the player.
+    Chase,
+    /// Telegraphs the coming attack.
+    Windup,
+    Attack,
+    /// Cannot act after an attack.
+    Recover,
+    /// Runs away from the player.
+    Flee,
+}
+
+/// What an enemy perceives of the player.
+struct Senses {
+    /// From the enemy to the player.
+    offset: Vec2,
+    line_of_sight: bool,
+    /// The player is too far or has been out of sight for too long.
+    lost: bool,
+    /// Current health relative to the maximum.
+    health_fraction: f32,
+}
+
+/// State machine of an enemy.
+#[derive(Component)]
+pub struct Behaviour {
+    pub state: AiState,
+    /// Time spent in the current state.
+    timer: Timer,
+    /// Time without line of sight.
+    unseen: Timer,
+    /// Center of the patrol.
+    home: Vec2,
+    patrol_direction: f32,
+    /// Direction of the current attack, fixed when the windup ends.
+    aim: Vec2,
+    /// The current attack has already been performed.
+    attacked: bool,
+    /// Low health made the enemy flee already, which it does only once.
+    fled: bool,
+}
+impl Behaviour {
+    pub fn new(home: Vec2) -> Self {
+        Behaviour {
+            state: AiState::Idle,
+            timer: Timer::from_seconds(IDLE_TIME, TimerMode::Once),
+            unseen: Timer::from_seconds(LOSE_SIGHT_TIME, TimerMode::Once),
+            home,
+            patrol_direction: 1.0,
+            aim: Vec2::ZERO,
+            attacked: false,
+            fled: false,
+        }
+    }
+    fn enter(&mut self, state: AiState, archetype: &Archetype) {
+        let duration = match state {
+            AiState::Idle => IDLE_TIME,
+            AiState::Patrol => PATROL_TIME,
+            AiState::Notice => NOTICE_TIME,
+            AiState::Windup => archetype.windup(),
+            AiState::Attack => archetype.attack_time(),
+            AiState::Recover => archetype.recovery(),
+            AiState::Flee => FLEE_TIME,
+            AiState::Chase => 0.0,
+        };
+        self.state = state;
+        self.timer = Timer::from_seconds(duration, TimerMode::Once);
+        self.attacked = false;
+    }
+    /// State to change to, if the current one is over.
+    fn next(&self, archetype: &Archetype, senses: &Senses) -> Option<AiState> {
+        let distance = senses.offset.length();
+        let sees_player = senses.line_of_sight && distance < archetype.sight_range();
+        let flee = (senses.health_fraction < archetype.flee_health() && !self.fled)
+            || (*archetype == Archetype::Ranged && distance < RANGED_MIN_DISTANCE);
+        let finished = self.timer.is_finished();
+        match self.state {
+            AiState::Idle | AiState::Patrol if sees_player => Some(AiState::Notice),
+            AiState::Idle if finished => Some(AiState::Patrol),
+            AiState::Patrol if finished => Some(AiState::Idle),
+            AiState::Notice if finished => Some(AiState::Chase),
+            AiState::Chase if senses.lost => Some(AiState::Patrol),
+            AiState::Chase if flee => Some(AiState::Flee),
+            AiState::Chase if senses.line_of_sight && distance < archetype.attack_range() => {
+                Some(AiState::Windup)
+            }
+            AiState::Windup if finished => Some(AiState::Attack),
+            AiState::Attack if finished => Some(AiState::Recover),
+            AiState::Recover if finished => Some(AiState::Chase),
+            AiState::Flee if finished => Some(AiState::Chase),
+            AiState::Flee if *archetype == Archetype::Ranged && distance > RANGED_SAFE_DISTANCE => {
+                Some(AiState::Chase)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Telegraph;
+
+impl Archetype {
+    fn sight_range(&self) -> f32 {
+        match self {
+            Self::Grunt => 8.0,
+            Self::Ranged => 10.0,
+            Self::Charger => 9.0,
+            Self::Tank => 7.0,
+            Self::Flyer => 9.0,
+        }
+    }
+    /// Distance from which the enemy starts its attack.
+    fn attack_range(&self) -> f32 {
+        match self {
+            Self::Grunt | Self::Tank => 1.5,
+            Self::Ranged => 7.0,
+            Self::Charger => 5.0,
+            Self::Flyer => 2.0,
+        }
+    }
+    fn windup(&self) -> f32 {
+        match self {
+            Self::Grunt | Self::Flyer => 0.4,
+            Self::Ranged => 0.5,
+            Self::Charger => 0.6,
+            Self::Tank => 0.8,
+        }
+    }
+    fn attack_time(&self) -> f32 {
+        match self {
+            Self::Grunt => 0.25,
+            Self::Ranged => 0.1,
+            Self::Charger => 0.5,
+            Self::Tank => 0.4,
+            Self::Flyer => 0.3,
+        }
+    }
+    fn recovery(&self) -> f32 {
+        match self {
+            Self::Grunt => 0.6,
+            Self::Ranged => 1.5,
+            Self::Charger => 1.0,
+            Self::Tank => 1.2,
+            Self::Flyer => 0.8,
+        }
+    }
+    /// Speed of the melee attack, a lunge along the aim.
+    fn lunge_speed(&self) -> f32 {
+        match self {
+            Self::Grunt => 7.0,
+            Self::Ranged => 0.0,
+            Self::Charger => 12.0,
+            Self::Tank => 4.0,
+            Self::Flyer => 8.0,
+        }
+    }
+    /// Fraction of the health below which the enemy flees once, zero for never.
+    fn flee_health(&self) -> f32 {
+        match self {
+            Self::Grunt => 0.25,
+            Self::Flyer => 0.3,
+            Self::Ranged | Self::Charger | Self::Tank => 0.0,
+        }
+    }
+}
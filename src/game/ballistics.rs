@@ -0,0 +1,144 @@
+use super::CollisionGroup;
+use super::Enemy;
+use super::Projectile;
+use super::skills::projectile;
+
+use avian2d::prelude::*;
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::cmp::Ordering;
+
+/// Homing projectiles do not notice enemies further away.
+const HOMING_RANGE: f32 = 8.0;
+/// Angle over which the fragments of a split projectile are spread.
+const SPLIT_SPREAD: f32 = 1.2;
+/// Damage of each fragment relative to the split projectile.
+const FRAGMENT_DAMAGE: f32 = 0.5;
+const FRAGMENT_LIFETIME: f32 = 0.3;
+
+/// Turns homing projectiles toward the nearest enemy they have not hit yet.
+pub fn home(
+    time_fixed: Res<Time>,
+    mut q_projectiles: Query<(
+        &Projectile,
+        &ProjectileModifiers,
+        &GlobalTransform,
+        &mut LinearVelocity,
+    )>,
+    q_enemies: Query<(Entity, &GlobalTransform), With<Enemy>>,
+) {
+    q_projectiles
+        .iter_mut()
+        .filter(|(_, modifiers, _, _)| modifiers.homing > 0.0)
+        .for_each(|(projectile, modifiers, transform, mut velocity)| {
+            let position = transform.translation().xy();
+            let target = q_enemies
+                .iter()
+                .filter(|(enemy, _)| !projectile.hit.contains(enemy))
+                .map(|(_, enemy_transform)| enemy_transform.translation().xy() - position)
+                .filter(|offset| offset.length() < HOMING_RANGE)
+                .min_by(|x, y| {
+                    x.length()
+                        .partial_cmp(&y.length())
+                        .unwrap_or(Ordering::Equal)
+                });
+            if let Some(offset) = target {
+                let max_turn = modifiers.homing * time_fixed.delta_secs();
+                let turn = velocity.0.angle_to(offset).clamp(-max_turn, max_turn);
+                velocity.0 = Vec2::from_angle(turn).rotate(velocity.0);
+            }
+        });
+}
+/// Bounces projectiles with ricochets left off the terrain they are about to fly into.
+pub fn ricochet(
+    time_fixed: Res<Time>,
+    spatial_query: SpatialQuery,
+    mut q_projectiles: Query<
+        (
+            &mut ProjectileModifiers,
+            &GlobalTransform,
+            &mut LinearVelocity,
+        ),
+        With<Projectile>,
+    >,
+) {
+    let filter = SpatialQueryFilter::from_mask(CollisionGroup::Terrain);
+    q_projectiles
+        .iter_mut()
+        .filter(|(modifiers, _, _)| modifiers.ricochet > 0)
+        .for_each(|(mut modifiers, transform, mut velocity)| {
+            let Ok(direction) = Dir2::new(velocity.0) else {
+                return;
+            };
+            let distance = velocity.length() * time_fixed.delta_secs();
+            if let Some(hit) = spatial_query.cast_ray(
+                transform.translation().xy(),
+                direction,
+                distance,
+                true,
+                &filter,
+            ) {
+                velocity.0 = velocity.0.reflect(hit.normal);
+                modifiers.ricochet -= 1;
+            }
+        });
+}
+
+/// Spawns the fragments of a projectile that split on a hit. Fragments do not split again
+/// and ignore everything the projectile already hit.
+pub fn split(
+    commands: &mut Commands,
+    projectile_data: &Projectile,
+    modifiers: &ProjectileModifiers,
+    position: Vec3,
+    velocity: Vec2,
+) {
+    let fragments = modifiers.split;
+    (0..fragments).for_each(|i| {
+        let angle = match fragments {
+            1 => 0.0,
+            _ => SPLIT_SPREAD * (i as f32 / (fragments - 1) as f32 - 0.5),
+        };
+        commands.spawn((
+            projectile(
+                Projectile {
+                    source: projectile_data.source,
+                    damage: projectile_data.damage.scaled(FRAGMENT_DAMAGE),
+                    hit: projectile_data.hit.clone(),
+                },
+                position,
+                Vec2::from_angle(angle).rotate(velocity),
+                FRAGMENT_LIFETIME,
+            ),
+            ProjectileModifiers {
+                split: 0,
+                ..*modifiers
+            },
+        ));
+    });
+}
+
+/// Changes how a projectile behaves, combined from the skill and the equipment of the caster.
+/// On a caster, these are the modifiers granted by its equipment.
+#[derive(Component, Deserialize, Default, Clone, Copy, Debug)]
+#[serde(default)]
+pub struct ProjectileModifiers {
+    /// Number of additional enemies the projectile passes through.
+    pub pierce: u32,
+    /// Number of bounces off the terrain.
+    pub ricochet: u32,
+    /// Turn rate toward the nearest enemy in radians per second, zero for no homing.
+    pub homing: f32,
+    /// Number of fragments the projectile splits into on its first hit.
+    pub split: u32,
+}
+impl ProjectileModifiers {
+    pub fn combine(self, other: Self) -> Self {
+        Self {
+            pierce: self.pierce + other.pierce,
+            ricochet: self.ricochet + other.ricochet,
+            homing: self.homing + other.homing,
+            split: self.split + other.split,
+        }
+    }
+}
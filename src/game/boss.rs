@@ -0,0 +1,446 @@
+use super::CollisionGroup;
+use super::Enemy;
+use super::Health;
+use super::Item;
+use super::MainState;
+use super::Player;
+use super::bullets::BulletPattern;
+use super::bullets::Emitter;
+use super::damage::ContactDamage;
+use super::damage::Damage;
+use super::damage::DamageType;
+use super::damage::Hitstun;
+use super::damage::Resistances;
+use super::enemy::Archetype;
+use super::enemy::spawn_enemy;
+use super::items::item;
+use super::stages::Door;
+use super::stages::Sealed;
+use super::stages::Stage;
+use super::status::StatusEffects;
+
+use avian2d::prelude::*;
+use bevy::prelude::*;
+
+/// Color of a boss winding up an attack.
+const TELEGRAPH_COLOR: Color = Color::WHITE;
+const SEALED_DOOR_COLOR: Color = Color::srgb(0.5, 0.0, 0.0);
+const DOOR_COLOR: Color = Color::BLACK;
+/// Seconds between two patterns, divided by the pace of the phase.
+const PATTERN_COOLDOWN: f32 = 2.0;
+/// Speed with which a boss walks toward the player between patterns.
+const WALK_SPEED: f32 = 1.0;
+const BOSS_SHOT_SPEED: f32 = 5.0;
+const BOSS_SHOT_DAMAGE: f32 = 10.0;
+const CHARGE_SPEED: f32 = 10.0;
+/// Distance from the boss at which summoned enemies appear.
+const SUMMON_DISTANCE: f32 = 2.0;
+/// Seconds the boss pauses after patterns which happen at once.
+const MIN_PATTERN_DURATION: f32 = 0.2;
+
+/// Spawns the boss of a boss room and seals the doors, once the player enters the room.
+pub fn enter_room(
+    mut commands: Commands,
+    q_rooms: Query<(Entity, &BossRoom, &GlobalTransform)>,
+    mut q_doors: Query<(Entity, &mut Sprite), With<Door>>,
+    q_player: Query<&GlobalTransform, With<Player>>,
+) -> Result {
+    let player_pos = q_player.single()?.translation().xy();
+    q_rooms.iter().for_each(|(entity, room, transform)| {
+        let center = transform.translation();
+        if !Rect::from_center_size(center.xy(), room.size).contains(player_pos) {
+            return;
+        }
+        commands.spawn(boss(room.boss, center));
+        q_doors.iter_mut().for_each(|(door, mut sprite)| {
+            commands.entity(door).insert(Sealed);
+            sprite.color = SEALED_DOOR_COLOR;
+        });
+        commands.entity(entity).despawn();
+    });
+
+    Ok(())
+}
+/// Switches the phase of bosses according to their health, and runs the patterns of the phase
+/// one after another, each telegraphed by a windup. Knocked back bosses wait for the hitstun.
+pub fn act(
+    time_fixed: Res<Time>,
+    mut commands: Commands,
+    mut q_bosses: Query<
+        (
+            Entity,
+            &mut Boss,
+            &Health,
+            &GlobalTransform,
+            &mut LinearVelocity,
+            &mut Sprite,
+            &StatusEffects,
+        ),
+        Without<Hitstun>,
+    >,
+    q_player: Query<&GlobalTransform, (With<Player>, Without<Boss>)>,
+) -> Result {
+    let player_pos = q_player.single()?.translation().xy();
+    let dt = time_fixed.delta();
+    q_bosses.iter_mut().for_each(
+        |(entity, mut boss, health, transform, mut v, mut sprite, effects)| {
+            let kind = boss.kind;
+            let phases = kind.phases();
+            let fraction = health.0 / kind.max_health();
+            let phase = phases
+                .iter()
+                .rposition(|phase| fraction <= phase.threshold)
+                .unwrap_or(0);
+            // A new phase starts over with its first pattern, cutting the current one short.
+            if phase != boss.phase {
+                boss.phase = phase;
+                boss.pattern = 0;
+                boss.enter(Step::Cooldown, PATTERN_COOLDOWN / phases[phase].pace);
+                sprite.color = kind.color();
+                commands.entity(entity).remove::<Emitter>();
+            }
+            if effects.stunned() {
+                v.x = 0.0;
+                return;
+            }
+            let phase = &phases[boss.phase];
+            let pattern = phase.patterns[boss.pattern % phase.patterns.len()];
+            let position = transform.translation();
+            let towards_player = (player_pos - position.xy()).normalize_or_zero();
+
+            let finished = boss.timer.tick(dt).is_finished();
+            match boss.step {
+                Step::Cooldown => {
+                    v.x = WALK_SPEED * effects.movement_factor() * towards_player.x.signum();
+                    if finished {
+                        v.x = 0.0;
+                        sprite.color = TELEGRAPH_COLOR;
+                        boss.enter(Step::Windup, pattern.windup());
+                    }
+                }
+                Step::Windup => {
+                    v.x = 0.0;
+                    if finished {
+                        sprite.color = kind.color();
+                        boss.aim = towards_player;
+                        pattern.start(&mut commands, entity, position);
+                        boss.enter(Step::Attack, pattern.duration());
+                    }
+                }
+                Step::Attack => {
+                    if let Pattern::Charge { .. } = pattern {
+                        v.x = CHARGE_SPEED * effects.movement_factor() * boss.aim.x.signum();
+                    }
+                    if finished {
+                        v.x = 0.0;
+                        boss.pattern = (boss.pattern + 1) % phase.patterns.len();
+                        boss.enter(Step::Cooldown, PATTERN_COOLDOWN / phase.pace);
+                    }
+                }
+            }
+        },
+    );
+
+    Ok(())
+}
+/// Announces defeated bosses, before `Health::system` despawns them.
+pub fn defeat(
+    q_bosses: Query<(&Boss, &Health, &GlobalTransform)>,
+    mut defeat_messages: MessageWriter<BossDefeated>,
+) {
+    q_bosses
+        .iter()
+        .filter(|(_, health, _)| health.0 <= 0.0)
+        .for_each(|(boss, _, transform)| {
+            defeat_messages.write(BossDefeated {
+                kind: boss.kind,
+                position: transform.translation(),
+            });
+        });
+}
+/// Opens the doors sealed by the arena lock.
+pub fn unseal_doors(
+    mut commands: Commands,
+    mut q_doors: Query<(Entity, &mut Sprite), With<Sealed>>,
+) {
+    q_doors.iter_mut().for_each(|(door, mut sprite)| {
+        commands.entity(door).remove::<Sealed>();
+        sprite.color = DOOR_COLOR;
+    });
+}
+/// Drops the loot of defeated bosses into the stage.
+pub fn drop_loot(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut defeat_messages: MessageReader<BossDefeated>,
+    q_stage: Query<(Entity, &GlobalTransform), With<Stage>>,
+) -> Result {
+    let (stage, stage_transform) = q_stage.single()?;
+    defeat_messages.read().for_each(|defeat| {
+        commands.spawn((
+            item(
+                defeat.kind.loot(),
+                &asset_server,
+                defeat.position - stage_transform.translation(),
+            ),
+            ChildOf(stage),
+        ));
+    });
+
+    Ok(())
+}
+
+fn boss(kind: BossKind, position: Vec3) -> impl Bundle {
+    let size = kind.size();
+    (
+        Name::new(kind.name()),
+        Enemy,
+        Boss::new(kind),
+        Health(kind.max_health()),
+        Resistances::default(),
+        StatusEffects::default(),
+        ContactDamage(Damage::new(kind.contact_damage(), DamageType::Slashing)),
+        Transform::from_translation(position),
+        Sprite::from_color(kind.color(), size),
+        (
+            RigidBody::Dynamic,
+            LockedAxes::new().lock_rotation(),
+            Collider::rectangle(size.x, size.y),
+            CollisionLayers::new(
+                CollisionGroup::Enemy,
+                [
+                    CollisionGroup::Player,
+                    CollisionGroup::Projectile,
+                    CollisionGroup::Summon,
+                    CollisionGroup::Hazard,
+                    CollisionGroup::Terrain,
+                ],
+            ),
+        ),
+        DespawnOnExit(MainState::Game),
+    )
+}
+
+/// Every boss there is.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BossKind {
+    Golem,
+}
+impl BossKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Golem => "Golem",
+        }
+    }
+    pub fn max_health(&self) -> f32 {
+        match self {
+            Self::Golem => 300.0,
+        }
+    }
+    fn size(&self) -> Vec2 {
+        match self {
+            Self::Golem => Vec2::splat(1.8),
+        }
+    }
+    fn color(&self) -> Color {
+        match self {
+            Self::Golem => Color::srgb(0.5, 0.4, 0.3),
+        }
+    }
+    fn contact_damage(&self) -> f32 {
+        match self {
+            Self::Golem => 30.0,
+        }
+    }
+    /// Item dropped when the boss is defeated.
+    fn loot(&self) -> Item {
+        match self {
+            Self::Golem => Item::Magnet,
+        }
+    }
+    /// Phases ordered by descending health threshold. The first one starts at full health.
+    fn phases(&self) -> &'static [Phase] {
+        match self {
+            Self::Golem => &[
+                Phase {
+                    threshold: 1.0,
+                    pace: 1.0,
+                    patterns: &[
+                        Pattern::Bullets {
+                            pattern: BulletPattern::Spread {
+                                count: 3,
+                                angle: 0.4,
+                            },
+                            volleys: 1,
+                            interval: 0.2,
+                        },
+                        Pattern::Charge { duration: 0.8 },
+                    ],
+                },
+                Phase {
+                    threshold: 0.6,
+                    pace: 1.3,
+                    patterns: &[
+                        Pattern::Bullets {
+                            pattern: BulletPattern::Spread {
+                                count: 5,
+                                angle: 0.8,
+                            },
+                            volleys: 3,
+                            interval: 0.4,
+                        },
+                        Pattern::Summon {
+                            archetype: Archetype::Grunt,
+                            count: 2,
+                        },
+                        Pattern::Charge { duration: 0.8 },
+                    ],
+                },
+                Phase {
+                    threshold: 0.3,
+                    pace: 1.6,
+                    patterns: &[
+                        Pattern::Bullets {
+                            pattern: BulletPattern::Ring { count: 12 },
+                            volleys: 2,
+                            interval: 0.5,
+                        },
+                        Pattern::Bullets {
+                            pattern: BulletPattern::Spiral {
+                                arms: 4,
+                                turn: 0.25,
+                            },
+                            volleys: 16,
+                            interval: 0.1,
+                        },
+                        Pattern::Charge { duration: 1.2 },
+                        Pattern::Summon {
+                            archetype: Archetype::Flyer,
+                            count: 3,
+                        },
+                    ],
+                },
+            ],
+        }
+    }
+}
+
+/// Part of a boss fight, which starts once the health of the boss drops to `threshold`.
+pub struct Phase {
+    /// Fraction of the maximum health.
+    threshold: f32,
+    /// Speeds up the patterns.
+    pace: f32,
+    /// Performed in order, over and over.
+    patterns: &'static [Pattern],
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Pattern {
+    /// Volleys of projectiles, `interval` seconds apart.
+    Bullets {
+        pattern: BulletPattern,
+        volleys: u32,
+        interval: f32,
+    },
+    /// Dash toward the player for `duration` seconds.
+    Charge { duration: f32 },
+    /// Enemies appearing around the boss.
+    Summon { archetype: Archetype, count: u32 },
+}
+impl Pattern {
+    /// Seconds of the telegraph before the pattern starts.
+    fn windup(&self) -> f32 {
+        match self {
+            Self::Bullets { .. } => 0.6,
+            Self::Charge { .. } => 0.8,
+            Self::Summon { .. } => 1.0,
+        }
+    }
+    /// Seconds the pattern lasts.
+    fn duration(&self) -> f32 {
+        match self {
+            Self::Charge { duration } => *duration,
+            Self::Bullets {
+                volleys, interval, ..
+            } => (*volleys as f32 * interval).max(MIN_PATTERN_DURATION),
+            Self::Summon { .. } => MIN_PATTERN_DURATION,
+        }
+    }
+    fn start(&self, commands: &mut Commands, boss: Entity, position: Vec3) {
+        match *self {
+            Self::Bullets {
+                pattern,
+                volleys,
+                interval,
+            } => {
+                commands.entity(boss).insert(
+                    Emitter::new(
+                        pattern,
+                        BOSS_SHOT_SPEED,
+                        Damage::new(BOSS_SHOT_DAMAGE, DamageType::Piercing),
+                        interval,
+                    )
+                    .with_volleys(volleys),
+                );
+            }
+            Self::Summon { archetype, count } => (0..count).for_each(|i| {
+                let side = if i % 2 == 0 { 1.0 } else { -1.0 };
+                let offset = side * SUMMON_DISTANCE * (1 + i / 2) as f32;
+                spawn_enemy(commands, archetype, position + offset * Vec3::X);
+            }),
+            Self::Charge { .. } => {}
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Step {
+    /// Walks toward the player before the next pattern.
+    Cooldown,
+    Windup,
+    Attack,
+}
+
+#[derive(Component)]
+pub struct Boss {
+    pub kind: BossKind,
+    /// Index into the phases of the kind.
+    phase: usize,
+    /// Index into the patterns of the current phase.
+    pattern: usize,
+    step: Step,
+    timer: Timer,
+    /// Direction to the player when the current pattern started.
+    aim: Vec2,
+}
+impl Boss {
+    fn new(kind: BossKind) -> Self {
+        Boss {
+            kind,
+            phase: 0,
+            pattern: 0,
+            step: Step::Cooldown,
+            timer: Timer::from_seconds(PATTERN_COOLDOWN, TimerMode::Once),
+            aim: Vec2::ZERO,
+        }
+    }
+    fn enter(&mut self, step: Step, seconds: f32) {
+        self.step = step;
+        self.timer = Timer::from_seconds(seconds, TimerMode::Once);
+    }
+}
+
+/// Area of a stage in which the player meets its boss. Entering it spawns the boss in its center.
+#[derive(Component)]
+pub struct BossRoom {
+    pub boss: BossKind,
+    pub size: Vec2,
+}
+
+/// Sent when a boss is defeated.
+#[derive(Message)]
+pub struct BossDefeated {
+    pub kind: BossKind,
+    pub position: Vec3,
+}
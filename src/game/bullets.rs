@@ -0,0 +1,215 @@
+use super::CollisionGroup;
+use super::Player;
+use super::damage::ContactDamage;
+use super::damage::Damage;
+use super::status::StatusEffects;
+use crate::MainState;
+use crate::utils::Lifetime;
+
+use avian2d::prelude::*;
+use bevy::prelude::*;
+use std::f32::consts::TAU;
+
+const BULLET_SIZE: f32 = 0.15;
+const BULLET_LIFETIME: f32 = 4.0;
+const BULLET_COLOR: Color = Color::srgb(0.6, 1.0, 0.3);
+
+/// Fires the volleys of emitters whenever their interval passes. Stunned emitters hold fire.
+/// Emitters with a limited number of volleys are removed after the last one.
+pub fn emit(
+    time_fixed: Res<Time>,
+    mut commands: Commands,
+    mut q_emitters: Query<(
+        Entity,
+        &mut Emitter,
+        &GlobalTransform,
+        Option<&StatusEffects>,
+    )>,
+    q_player: Query<&GlobalTransform, With<Player>>,
+) -> Result {
+    let player_pos = q_player.single()?.translation().xy();
+    let dt = time_fixed.delta();
+    q_emitters
+        .iter_mut()
+        .for_each(|(entity, mut emitter, transform, effects)| {
+            if effects.is_some_and(|effects| effects.stunned()) {
+                return;
+            }
+            // The first volley goes off right away.
+            if emitter.fired > 0 && !emitter.timer.tick(dt).just_finished() {
+                return;
+            }
+            let position = transform.translation();
+            let aim = (player_pos - position.xy()).normalize_or(Vec2::X);
+            fire(
+                &mut commands,
+                emitter.pattern,
+                emitter.fired,
+                position,
+                aim,
+                emitter.speed,
+                emitter.damage,
+            );
+            emitter.fired += 1;
+            if emitter
+                .volleys
+                .is_some_and(|volleys| emitter.fired >= volleys)
+            {
+                commands.entity(entity).remove::<Emitter>();
+            }
+        });
+
+    Ok(())
+}
+/// Despawns enemy projectiles which touched the player or a summon, or are about to fly into
+/// terrain. Their damage is dealt by `player::hit` and `summons::hit`, like contact damage of
+/// enemies.
+pub fn impact(
+    time_fixed: Res<Time>,
+    mut commands: Commands,
+    spatial_query: SpatialQuery,
+    q_projectiles: Query<
+        (
+            Entity,
+            &GlobalTransform,
+            &LinearVelocity,
+            &CollidingEntities,
+        ),
+        With<EnemyProjectile>,
+    >,
+) {
+    let filter = SpatialQueryFilter::from_mask(CollisionGroup::Terrain);
+    q_projectiles
+        .iter()
+        .for_each(|(entity, transform, velocity, colliding_entities)| {
+            let hits_terrain = Dir2::new(velocity.0).is_ok_and(|direction| {
+                spatial_query
+                    .cast_ray(
+                        transform.translation().xy(),
+                        direction,
+                        velocity.length() * time_fixed.delta_secs(),
+                        true,
+                        &filter,
+                    )
+                    .is_some()
+            });
+            if hits_terrain || !colliding_entities.is_empty() {
+                commands.entity(entity).despawn();
+            }
+        });
+}
+
+/// Fires one volley of the pattern. `volley` is the number of volleys fired before, `aim` the
+/// direction to the player.
+pub fn fire(
+    commands: &mut Commands,
+    pattern: BulletPattern,
+    volley: u32,
+    position: Vec3,
+    aim: Vec2,
+    speed: f32,
+    damage: Damage,
+) {
+    pattern
+        .directions(aim, volley)
+        .into_iter()
+        .for_each(|direction| {
+            commands.spawn(projectile(position, speed * direction, damage));
+        });
+}
+/// Projectile of an enemy, which hurts the player and summons on contact and breaks on terrain.
+pub fn projectile(position: Vec3, velocity: Vec2, damage: Damage) -> impl Bundle {
+    (
+        Name::new("Enemy Projectile"),
+        EnemyProjectile,
+        ContactDamage(damage),
+        Sprite::from_color(BULLET_COLOR, Vec2::splat(BULLET_SIZE)),
+        Transform::from_translation(position),
+        RigidBody::Kinematic,
+        Sensor,
+        Collider::rectangle(BULLET_SIZE, BULLET_SIZE),
+        LinearVelocity(velocity),
+        CollidingEntities::default(),
+        CollisionLayers::new(
+            CollisionGroup::EnemyProjectile,
+            [
+                CollisionGroup::Player,
+                CollisionGroup::Summon,
+                CollisionGroup::Terrain,
+            ],
+        ),
+        Lifetime::new(BULLET_LIFETIME),
+        DespawnOnExit(MainState::Game),
+    )
+}
+
+/// Shape of one volley of enemy projectiles.
+#[derive(Clone, Copy, Debug)]
+pub enum BulletPattern {
+    /// One projectile at the player.
+    Aimed,
+    /// Projectiles spread evenly over `angle` radians around the direction to the player.
+    Spread { count: u32, angle: f32 },
+    /// Projectiles evenly around the emitter, the first one at the player.
+    Ring { count: u32 },
+    /// Projectiles evenly around the emitter, turned by `turn` radians with every volley.
+    Spiral { arms: u32, turn: f32 },
+}
+impl BulletPattern {
+    /// Directions of the projectiles of a volley.
+    fn directions(&self, aim: Vec2, volley: u32) -> Vec<Vec2> {
+        match *self {
+            Self::Aimed => vec![aim],
+            Self::Spread { count, angle } => (0..count)
+                .map(|i| {
+                    let offset = match count {
+                        1 => 0.0,
+                        _ => angle * (i as f32 / (count - 1) as f32 - 0.5),
+                    };
+                    Vec2::from_angle(offset).rotate(aim)
+                })
+                .collect(),
+            Self::Ring { count } => (0..count)
+                .map(|i| Vec2::from_angle(TAU * i as f32 / count as f32).rotate(aim))
+                .collect(),
+            Self::Spiral { arms, turn } => (0..arms)
+                .map(|i| Vec2::from_angle(TAU * i as f32 / arms as f32 + turn * volley as f32))
+                .collect(),
+        }
+    }
+}
+
+/// Fires volleys of a pattern every `interval` seconds.
+#[derive(Component)]
+pub struct Emitter {
+    pattern: BulletPattern,
+    speed: f32,
+    damage: Damage,
+    timer: Timer,
+    /// Volleys fired so far.
+    fired: u32,
+    /// Volleys fired in total, endless if `None`.
+    volleys: Option<u32>,
+}
+impl Emitter {
+    pub fn new(pattern: BulletPattern, speed: f32, damage: Damage, interval: f32) -> Self {
+        Emitter {
+            pattern,
+            speed,
+            damage,
+            timer: Timer::from_seconds(interval, TimerMode::Repeating),
+            fired: 0,
+            volleys: None,
+        }
+    }
+    pub fn with_volleys(self, volleys: u32) -> Self {
+        Emitter {
+            volleys: Some(volleys),
+            ..self
+        }
+    }
+}
+
+/// Marks projectiles of enemies.
+#[derive(Component)]
+pub struct EnemyProjectile;
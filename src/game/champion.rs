@@ -0,0 +1,133 @@
+use super::Stats;
+use super::ballistics::ProjectileModifiers;
+use super::damage::IFrames;
+use super::damage::Resistances;
+use super::platformer::PlatformerController;
+use super::skills::Skill;
+use super::stats::StatModifier;
+use crate::assets::PreloadedAssets;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Loads definitions of all champions, so that they are ready before the game starts.
+pub fn load(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut preloaded: ResMut<PreloadedAssets>,
+) {
+    let handles = Champion::ALL
+        .iter()
+        .map(|champion| (*champion, asset_server.load(champion.path())))
+        .collect::<HashMap<Champion, Handle<ChampionDefinition>>>();
+    preloaded
+        .0
+        .extend(handles.values().map(|handle| handle.clone().untyped()));
+    commands.insert_resource(ChampionHandles(handles));
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, Debug)]
+pub enum Champion {
+    #[default]
+    Soldier,
+    Engineer,
+}
+impl Champion {
+    const ALL: [Champion; 2] = [Champion::Soldier, Champion::Engineer];
+
+    fn path(&self) -> &'static str {
+        match self {
+            Self::Soldier => "champions/soldier.champion.ron",
+            Self::Engineer => "champions/engineer.champion.ron",
+        }
+    }
+    pub fn cycle(&mut self) {
+        *self = match self {
+            Self::Soldier => Self::Engineer,
+            Self::Engineer => Self::Soldier,
+        }
+    }
+}
+impl std::fmt::Display for Champion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Soldier => write!(f, "Soldier"),
+            Self::Engineer => write!(f, "Engineer"),
+        }
+    }
+}
+
+/// Champion that will be used in the next run.
+#[derive(Resource, Default)]
+pub struct SelectedChampion(pub Champion);
+
+#[derive(Resource)]
+pub struct ChampionHandles(HashMap<Champion, Handle<ChampionDefinition>>);
+
+/// Definition of the selected champion, for systems that build the player or its HUD.
+#[derive(SystemParam)]
+pub struct SelectedDefinition<'w> {
+    selected: Res<'w, SelectedChampion>,
+    handles: Res<'w, ChampionHandles>,
+    definitions: Res<'w, Assets<ChampionDefinition>>,
+}
+impl SelectedDefinition<'_> {
+    pub fn get(&self) -> Result<&ChampionDefinition> {
+        self.handles
+            .0
+            .get(&self.selected.0)
+            .and_then(|handle| self.definitions.get(handle))
+            .ok_or_else(|| format!("{} definition is not loaded", self.selected.0).into())
+    }
+}
+
+/// Loaded from `*.champion.ron` files in `assets/champions`.
+#[derive(Asset, TypePath, Deserialize)]
+pub struct ChampionDefinition {
+    /// Base stats, before any modifiers.
+    pub stats: Stats,
+    /// Modifiers of the stats the champion always has.
+    #[serde(default)]
+    pub passives: Vec<StatModifier>,
+    #[serde(default)]
+    pub resistances: Resistances,
+    /// Invulnerability after being hit.
+    #[serde(default)]
+    pub iframes: IFrames,
+    pub sprite: SpriteDefinition,
+    /// Width and height of the rectangular collider in meters.
+    pub collider: (f32, f32),
+    #[serde(default)]
+    pub controller: PlatformerController,
+    /// Skills in slots A, B, C and D.
+    pub skills: [SkillDefinition; 4],
+}
+
+#[derive(Deserialize)]
+pub struct SpriteDefinition {
+    /// Path to the image, plain white rectangle is used if there is none.
+    #[serde(default)]
+    pub image: Option<String>,
+    /// Width and height in meters.
+    pub size: (f32, f32),
+}
+impl SpriteDefinition {
+    pub fn sprite(&self, asset_server: &AssetServer) -> Sprite {
+        let size = Vec2::from(self.size);
+        match &self.image {
+            Some(path) => crate::utils::image_size_to_sprite(asset_server.load(path), size),
+            None => Sprite::from_color(Color::WHITE, size),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SkillDefinition {
+    pub skill: Skill,
+    pub icon: String,
+    /// Applied to the projectiles of the skill, on top of those from the equipment.
+    #[serde(default)]
+    pub modifiers: ProjectileModifiers,
+}
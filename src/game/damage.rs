@@ -0,0 +1,338 @@
+use super::Health;
+use super::Stats;
+use super::skills::Marked;
+use super::summons::Summon;
+
+use avian2d::prelude::*;
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+/// Color of everything related to critical hits.
+pub const CRIT_COLOR: Color = Color::srgb(1.0, 0.85, 0.1);
+
+/// Applies all hits to their targets, reduced by the target's `Resistances` and armor,
+/// absorbed by the `Shield` first and then subtracted from `Health`, and pushes the targets
+/// away. Invulnerable targets ignore hits, and targets with `IFrames` become invulnerable after
+/// the first hit that is not periodic.
+pub fn apply_hits(
+    mut commands: Commands,
+    mut hit_messages: MessageReader<Hit>,
+    mut q_targets: Query<
+        (
+            &mut Health,
+            Option<&mut Shield>,
+            Option<&Stats>,
+            Option<&Resistances>,
+            Option<&Marked>,
+            Option<&IFrames>,
+            Forces,
+        ),
+        Without<Invulnerable>,
+    >,
+    q_summons: Query<(), With<Summon>>,
+) {
+    // Invulnerability is inserted by commands, so it has to be tracked for hits in the same tick.
+    let mut hurt = HashSet::new();
+    hit_messages.read().for_each(|hit| {
+        if hurt.contains(&hit.target) && !hit.periodic {
+            return;
+        }
+        let Ok((mut health, shield, stats, resistances, marked, iframes, mut forces)) =
+            q_targets.get_mut(hit.target)
+        else {
+            return;
+        };
+
+        let damage = match marked {
+            Some(marked) if q_summons.contains(hit.source) => marked.amplify(hit.damage),
+            _ => hit.damage,
+        };
+        let mut amount = match resistances {
+            Some(resistances) => resistances.reduce(damage),
+            None => damage.amount,
+        };
+        if let Some(stats) = stats {
+            amount = stats.mitigate(amount);
+        }
+        if let Some(mut shield) = shield {
+            amount = shield.absorb(amount);
+        }
+        health.0 -= amount;
+
+        if hit.knockback != Vec2::ZERO {
+            forces.apply_linear_impulse(hit.knockback);
+        }
+        if hit.hitstun > 0.0 {
+            commands
+                .entity(hit.target)
+                .try_insert(Hitstun::new(hit.hitstun));
+        }
+        if let Some(iframes) = iframes
+            && !hit.periodic
+        {
+            hurt.insert(hit.target);
+            commands
+                .entity(hit.target)
+                .try_insert(Invulnerable::blinking(
+                    iframes.duration,
+                    iframes.blink_interval,
+                ));
+        }
+    });
+}
+/// Regenerates `Health` and recharges `Shield` of entities with `Stats`.
+pub fn regenerate(
+    time_fixed: Res<Time>,
+    mut q_regenerating: Query<(&mut Health, Option<&mut Shield>, &Stats)>,
+) {
+    let dt = time_fixed.delta_secs();
+    q_regenerating
+        .iter_mut()
+        .for_each(|(mut health, shield, stats)| {
+            // Dead stays dead.
+            if health.0 > 0.0 {
+                health.0 = (health.0 + stats.health_regen * dt).min(stats.max_health);
+            }
+            if let Some(mut shield) = shield {
+                shield.since_hit += dt;
+                if shield.since_hit >= stats.shield_recharge_delay {
+                    shield.amount =
+                        (shield.amount + stats.shield_recharge_rate * dt).min(stats.shield);
+                }
+            }
+        });
+}
+pub fn end_hitstun(
+    time_fixed: Res<Time>,
+    mut commands: Commands,
+    mut q_stunned: Query<(Entity, &mut Hitstun)>,
+) {
+    q_stunned.iter_mut().for_each(|(entity, mut hitstun)| {
+        if hitstun.0.tick(time_fixed.delta()).is_finished() {
+            commands.entity(entity).remove::<Hitstun>();
+        }
+    });
+}
+pub fn end_invulnerability(
+    time_fixed: Res<Time>,
+    mut commands: Commands,
+    mut q_invulnerable: Query<(Entity, &mut Invulnerable, Option<&mut Visibility>)>,
+) {
+    q_invulnerable
+        .iter_mut()
+        .for_each(|(entity, mut invulnerable, visibility)| {
+            if invulnerable.timer.tick(time_fixed.delta()).is_finished() {
+                commands.entity(entity).remove::<Invulnerable>();
+                if let Some(mut visibility) = visibility {
+                    *visibility = Visibility::Inherited;
+                }
+            }
+        });
+}
+/// Blinks invulnerable entities, unless the invulnerability comes from something already visible,
+/// like the dash.
+pub fn blink(mut q_invulnerable: Query<(&Invulnerable, &mut Visibility)>) {
+    q_invulnerable
+        .iter_mut()
+        .for_each(|(invulnerable, mut visibility)| {
+            let Some(interval) = invulnerable.blink_interval else {
+                return;
+            };
+            let phase = (invulnerable.timer.elapsed_secs() / interval) as u32;
+            visibility.set_if_neq(if phase % 2 == 0 {
+                Visibility::Hidden
+            } else {
+                Visibility::Inherited
+            });
+        });
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum DamageType {
+    Piercing,
+    Slashing,
+    Explosive,
+    Fire,
+    Electric,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Damage {
+    pub amount: f32,
+    pub kind: DamageType,
+    pub critical: bool,
+}
+impl Damage {
+    pub fn new(amount: f32, kind: DamageType) -> Self {
+        Self {
+            amount,
+            kind,
+            critical: false,
+        }
+    }
+    pub fn critical(self, multiplier: f32) -> Self {
+        Self {
+            amount: multiplier * self.amount,
+            critical: true,
+            ..self
+        }
+    }
+    pub fn scaled(self, factor: f32) -> Self {
+        Self {
+            amount: factor * self.amount,
+            ..self
+        }
+    }
+}
+
+/// Linearly decreases damage of a projectile with the distance travelled from `origin`.
+#[derive(Component)]
+pub struct DamageFalloff {
+    pub origin: Vec2,
+    /// Distance at which the damage drops to zero.
+    pub range: f32,
+}
+impl DamageFalloff {
+    pub fn fraction(&self, position: Vec2) -> f32 {
+        (1.0 - self.origin.distance(position) / self.range).max(0.0)
+    }
+    pub fn apply(&self, damage: Damage, position: Vec2) -> Damage {
+        damage.scaled(self.fraction(position))
+    }
+}
+
+/// Damage dealt by the `source` to the `target`, which will be subtracted from its `Health`.
+#[derive(Message)]
+pub struct Hit {
+    /// Entity that dealt the damage, which might no longer exist.
+    pub source: Entity,
+    pub target: Entity,
+    pub damage: Damage,
+    /// Impulse applied to the target.
+    pub knockback: Vec2,
+    /// Seconds during which the target doesn't control its own velocity.
+    pub hitstun: f32,
+    /// Damage over time, which does not grant invulnerability.
+    pub periodic: bool,
+}
+impl Hit {
+    pub fn new(source: Entity, target: Entity, damage: Damage) -> Self {
+        Self {
+            source,
+            target,
+            damage,
+            knockback: Vec2::ZERO,
+            hitstun: 0.0,
+            periodic: false,
+        }
+    }
+    pub fn with_knockback(self, knockback: Vec2, hitstun: f32) -> Self {
+        Self {
+            knockback,
+            hitstun,
+            ..self
+        }
+    }
+    pub fn periodic(self) -> Self {
+        Self {
+            periodic: true,
+            ..self
+        }
+    }
+}
+
+/// Absorbs damage before it reaches `Health`, recharging after a while without hits.
+#[derive(Component, Default)]
+pub struct Shield {
+    pub amount: f32,
+    /// Seconds since the shield last absorbed or let through any damage.
+    since_hit: f32,
+}
+impl Shield {
+    pub fn new(amount: f32) -> Self {
+        Self {
+            amount,
+            since_hit: 0.0,
+        }
+    }
+    /// Returns the damage that got through the shield.
+    fn absorb(&mut self, amount: f32) -> f32 {
+        if amount <= 0.0 {
+            return amount;
+        }
+        self.since_hit = 0.0;
+        let absorbed = amount.min(self.amount);
+        self.amount -= absorbed;
+        amount - absorbed
+    }
+}
+
+/// Damage dealt by an entity to whatever it touches.
+#[derive(Component, Clone, Copy)]
+pub struct ContactDamage(pub Damage);
+
+/// Invulnerability window the entity gets after being hit.
+#[derive(Component, Deserialize, Clone)]
+#[serde(default)]
+pub struct IFrames {
+    pub duration: f32,
+    /// Seconds between toggling the visibility while invulnerable.
+    pub blink_interval: f32,
+}
+impl Default for IFrames {
+    fn default() -> Self {
+        Self {
+            duration: 1.0,
+            blink_interval: 0.1,
+        }
+    }
+}
+
+/// Entity ignores all hits until the timer finishes.
+#[derive(Component)]
+pub struct Invulnerable {
+    timer: Timer,
+    blink_interval: Option<f32>,
+}
+impl Invulnerable {
+    pub fn new(duration: f32) -> Self {
+        Self {
+            timer: Timer::from_seconds(duration, TimerMode::Once),
+            blink_interval: None,
+        }
+    }
+    pub fn blinking(duration: f32, blink_interval: f32) -> Self {
+        Self {
+            blink_interval: Some(blink_interval),
+            ..Self::new(duration)
+        }
+    }
+    /// Makes the invulnerability last at least `duration` more seconds.
+    pub fn extend(&mut self, duration: f32) {
+        let remaining = self.timer.remaining_secs();
+        if duration > remaining {
+            self.timer = Timer::from_seconds(duration, TimerMode::Once);
+        }
+    }
+}
+
+/// Target was knocked back and doesn't control its own velocity until the timer finishes.
+#[derive(Component)]
+pub struct Hitstun(Timer);
+impl Hitstun {
+    pub fn new(duration: f32) -> Self {
+        Hitstun(Timer::from_seconds(duration, TimerMode::Once))
+    }
+}
+
+/// Fraction of the damage of a given type that is ignored, 1.0 being immunity.
+/// Negative values make the entity take more damage of that type.
+#[derive(Component, Deserialize, Default, Clone)]
+pub struct Resistances(HashMap<DamageType, f32>);
+impl Resistances {
+    pub fn reduce(&self, damage: Damage) -> f32 {
+        damage.amount * (1.0 - self.0.get(&damage.kind).unwrap_or(&0.0))
+    }
+}
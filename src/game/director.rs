@@ -0,0 +1,357 @@
+use super::Enemy;
+use super::Player;
+use super::Run;
+use super::RunRng;
+use super::enemy::Archetype;
+use super::enemy::spawn_enemy;
+use crate::utils::Rng;
+
+use bevy::prelude::*;
+use std::time::Duration;
+
+/// Depth of the spawned enemies.
+const SPAWN_Z: f32 = 0.5;
+
+/// Spawns the enemies ordered by the directors of the stages.
+pub fn direct(
+    time: Res<Time>,
+    mut commands: Commands,
+    mut rngs: ResMut<RunRng>,
+    mut q_directors: Query<(&mut Director, &DirectorConfig, &GlobalTransform)>,
+    q_enemies: Query<(), With<Enemy>>,
+    q_player: Query<&GlobalTransform, With<Player>>,
+    q_run: Query<&Run>,
+) -> Result {
+    let run_time = q_run.single()?.0.elapsed_secs();
+    let player_pos = q_player.single()?.translation().xy();
+    let mut alive = q_enemies.iter().count();
+    q_directors
+        .iter_mut()
+        .for_each(|(mut director, config, transform)| {
+            let origin = transform.translation();
+            let spawns = director.update(
+                config,
+                time.delta(),
+                run_time,
+                alive,
+                player_pos - origin.xy(),
+                &mut rngs.director,
+            );
+            alive += spawns.len();
+            spawns.into_iter().for_each(|spawn| {
+                spawn_enemy(
+                    &mut commands,
+                    spawn.archetype,
+                    origin + spawn.position.extend(SPAWN_Z),
+                );
+            });
+        });
+
+    Ok(())
+}
+
+/// How the director of a stage spends its budget. Positions are relative to the stage.
+#[derive(Component, Clone)]
+pub struct DirectorConfig {
+    /// How many stages deep the stage is, which makes its waves bigger.
+    pub depth: u32,
+    /// Archetypes the director chooses from.
+    pub archetypes: Vec<Archetype>,
+    pub spawn_points: Vec<Vec2>,
+    /// Spawn points closer to the player are not used.
+    pub min_player_distance: f32,
+    /// Budget gained per second at the start of the run.
+    pub income: f32,
+    /// Fraction by which the income grows every minute of the run.
+    pub income_growth: f32,
+    /// Fraction by which the income grows with every stage of depth.
+    pub depth_growth: f32,
+    /// Budget saved up at most, so that a long wait does not spawn a horde at once.
+    pub max_budget: f32,
+    /// Enemies alive at most, counting those of other directors.
+    pub max_alive: usize,
+    /// Seconds of a wave, during which the director earns and spends.
+    pub wave_duration: f32,
+    /// Seconds between waves, during which nothing spawns.
+    pub break_duration: f32,
+}
+impl Default for DirectorConfig {
+    fn default() -> Self {
+        DirectorConfig {
+            depth: 0,
+            archetypes: Archetype::ALL.to_vec(),
+            spawn_points: Vec::new(),
+            min_player_distance: 8.0,
+            income: 0.4,
+            income_growth: 0.5,
+            depth_growth: 0.25,
+            max_budget: 10.0,
+            max_alive: 12,
+            wave_duration: 30.0,
+            break_duration: 10.0,
+        }
+    }
+}
+impl DirectorConfig {
+    /// Budget per second after `run_time` seconds of the run.
+    pub fn income(&self, run_time: f32) -> f32 {
+        self.income
+            * (1.0 + self.income_growth * run_time / 60.0)
+            * (1.0 + self.depth_growth * self.depth as f32)
+    }
+    /// Random spawn point far enough from the player, if there is any.
+    pub fn spawn_point(&self, player: Vec2, rng: &mut Rng) -> Option<Vec2> {
+        let points = self
+            .spawn_points
+            .iter()
+            .filter(|point| point.distance(player) >= self.min_player_distance)
+            .collect::<Vec<_>>();
+        if points.is_empty() {
+            return None;
+        }
+        Some(*points[rng.next_u64() as usize % points.len()])
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WavePhase {
+    Wave,
+    Break,
+}
+
+/// Enemy the director decided to spawn.
+#[derive(Clone, Copy, Debug)]
+pub struct Spawn {
+    pub archetype: Archetype,
+    /// Relative to the stage.
+    pub position: Vec2,
+}
+
+/// Spends a budget, growing with the run time and the depth of the stage, on enemies.
+/// Waves alternate with breaks.
+#[derive(Component)]
+pub struct Director {
+    budget: f32,
+    /// Number of the current or last wave, starting at 1.
+    pub wave: u32,
+    pub phase: WavePhase,
+    timer: Timer,
+    /// Archetype the director saves up for.
+    next: Option<Archetype>,
+}
+impl Director {
+    /// Starts with a break, so that the player can look around.
+    pub fn new(config: &DirectorConfig) -> Self {
+        Director {
+            budget: 0.0,
+            wave: 0,
+            phase: WavePhase::Break,
+            timer: Timer::from_seconds(config.break_duration, TimerMode::Once),
+            next: None,
+        }
+    }
+    /// Advances the director by `dt`, and returns the enemies to spawn. Depends on no world,
+    /// only on the arguments: `alive` enemies exist, and the player is at `player`, relative
+    /// to the stage.
+    pub fn update(
+        &mut self,
+        config: &DirectorConfig,
+        dt: Duration,
+        run_time: f32,
+        alive: usize,
+        player: Vec2,
+        rng: &mut Rng,
+    ) -> Vec<Spawn> {
+        if self.timer.tick(dt).is_finished() {
+            let (phase, duration) = match self.phase {
+                WavePhase::Wave => (WavePhase::Break, config.break_duration),
+                WavePhase::Break => {
+                    self.wave += 1;
+                    (WavePhase::Wave, config.wave_duration)
+                }
+            };
+            self.phase = phase;
+            self.timer = Timer::from_seconds(duration, TimerMode::Once);
+            // What is left of a wave does not carry over into the next one.
+            self.budget = 0.0;
+        }
+        // Archetypes costing more than the budget can ever hold would stall the director.
+        let affordable = config
+            .archetypes
+            .iter()
+            .filter(|archetype| archetype.cost() <= config.max_budget)
+            .collect::<Vec<_>>();
+        if self.phase == WavePhase::Break || affordable.is_empty() {
+            return Vec::new();
+        }
+
+        self.budget =
+            (self.budget + config.income(run_time) * dt.as_secs_f32()).min(config.max_budget);
+        let mut spawns = Vec::new();
+        while alive + spawns.len() < config.max_alive {
+            let archetype = *self
+                .next
+                .get_or_insert_with(|| *affordable[rng.next_u64() as usize % affordable.len()]);
+            if archetype.cost() > self.budget {
+                break;
+            }
+            let Some(position) = config.spawn_point(player, rng) else {
+                break;
+            };
+            self.budget -= archetype.cost();
+            self.next = None;
+            spawns.push(Spawn {
+                archetype,
+                position,
+            });
+        }
+        spawns
+    }
+}
+
+impl Archetype {
+    /// Budget a director spends on an enemy of the archetype.
+    pub fn cost(&self) -> f32 {
+        match self {
+            Self::Grunt => 1.0,
+            Self::Ranged | Self::Flyer => 2.0,
+            Self::Charger => 3.0,
+            Self::Tank => 5.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+    const PLAYER: Vec2 = Vec2::ZERO;
+
+    /// Grunts spawning far from the player, with a short break before the first wave.
+    fn config() -> DirectorConfig {
+        DirectorConfig {
+            archetypes: vec![Archetype::Grunt],
+            spawn_points: vec![Vec2::new(20.0, 0.0)],
+            break_duration: 1.0,
+            wave_duration: 100.0,
+            ..Default::default()
+        }
+    }
+    /// Director in the middle of its first wave.
+    fn in_wave(config: &DirectorConfig) -> Director {
+        Director {
+            wave: 1,
+            phase: WavePhase::Wave,
+            timer: Timer::from_seconds(config.wave_duration, TimerMode::Once),
+            ..Director::new(config)
+        }
+    }
+    /// Budget after one second of a wave, in which nothing spawns.
+    fn budget(config: &DirectorConfig, run_time: f32) -> f32 {
+        let config = DirectorConfig {
+            max_alive: 0,
+            ..config.clone()
+        };
+        let mut director = in_wave(&config);
+        director.update(&config, SECOND, run_time, 0, PLAYER, &mut Rng::seeded(0));
+        director.budget
+    }
+
+    #[test]
+    fn nothing_spawns_during_a_break() {
+        let config = DirectorConfig {
+            income: 100.0,
+            ..config()
+        };
+        let mut director = Director::new(&config);
+        let mut rng = Rng::seeded(0);
+        let spawns = director.update(&config, SECOND / 2, 0.0, 0, PLAYER, &mut rng);
+        assert!(spawns.is_empty());
+        assert_eq!(director.phase, WavePhase::Break);
+
+        director.update(&config, SECOND / 2, 0.5, 0, PLAYER, &mut rng);
+        assert_eq!(director.phase, WavePhase::Wave);
+        assert_eq!(director.wave, 1);
+    }
+    #[test]
+    fn budget_grows_with_run_time_and_depth() {
+        let config = config();
+        assert!(budget(&config, 600.0) > budget(&config, 0.0));
+        let deeper = DirectorConfig {
+            depth: 3,
+            ..config.clone()
+        };
+        assert!(budget(&deeper, 0.0) > budget(&config, 0.0));
+    }
+    #[test]
+    fn max_alive_is_respected() {
+        let config = DirectorConfig {
+            income: 100.0,
+            max_alive: 12,
+            ..config()
+        };
+        let mut rng = Rng::seeded(0);
+        let spawns = in_wave(&config).update(&config, SECOND, 0.0, 10, PLAYER, &mut rng);
+        assert_eq!(spawns.len(), 2);
+        let spawns = in_wave(&config).update(&config, SECOND, 0.0, 12, PLAYER, &mut rng);
+        assert!(spawns.is_empty());
+    }
+    #[test]
+    fn max_budget_is_respected() {
+        let config = DirectorConfig {
+            income: 100.0,
+            max_budget: 3.0,
+            ..config()
+        };
+        let mut director = in_wave(&config);
+        let spawns = director.update(&config, SECOND, 0.0, 0, PLAYER, &mut Rng::seeded(0));
+        // Grunts cost 1 each.
+        assert_eq!(spawns.len(), 3);
+        assert!(director.budget < 1.0);
+    }
+    #[test]
+    fn spawn_points_close_to_the_player_are_skipped() {
+        let config = DirectorConfig {
+            income: 100.0,
+            spawn_points: vec![Vec2::new(1.0, 0.0), Vec2::new(20.0, 0.0)],
+            ..config()
+        };
+        let mut rng = Rng::seeded(0);
+        let spawns = in_wave(&config).update(&config, SECOND, 0.0, 0, PLAYER, &mut rng);
+        assert!(!spawns.is_empty());
+        assert!(
+            spawns
+                .iter()
+                .all(|spawn| spawn.position == Vec2::new(20.0, 0.0))
+        );
+
+        let close = DirectorConfig {
+            spawn_points: vec![Vec2::new(1.0, 0.0)],
+            ..config
+        };
+        let spawns = in_wave(&close).update(&close, SECOND, 0.0, 0, PLAYER, &mut rng);
+        assert!(spawns.is_empty());
+    }
+    #[test]
+    fn unaffordable_archetypes_do_not_stall_the_director() {
+        let config = DirectorConfig {
+            income: 100.0,
+            max_budget: 3.0,
+            archetypes: vec![Archetype::Tank, Archetype::Grunt],
+            ..config()
+        };
+        let mut director = in_wave(&config);
+        let mut rng = Rng::seeded(0);
+        let spawns = (0..10)
+            .flat_map(|_| director.update(&config, SECOND, 0.0, 0, PLAYER, &mut rng))
+            .collect::<Vec<_>>();
+        // Three Grunts every second, none of them waiting for a Tank.
+        assert_eq!(spawns.len(), 30);
+        assert!(
+            spawns
+                .iter()
+                .all(|spawn| spawn.archetype == Archetype::Grunt)
+        );
+    }
+}
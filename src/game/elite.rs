@@ -0,0 +1,294 @@
+use super::CollisionGroup;
+use super::Health;
+use super::Item;
+use super::MainState;
+use super::RunRng;
+use super::damage::ContactDamage;
+use super::damage::Damage;
+use super::damage::DamageType;
+use super::damage::Shield;
+use super::enemy::Archetype;
+use super::enemy::MaxHealth;
+use super::enemy::spawn_enemy;
+use super::explosion::Explosion;
+use super::hazard::HazardKind;
+use super::hazard::hazard;
+use super::items::item;
+use super::stages::Stage;
+use crate::assets::PreloadedAssets;
+use crate::utils::Lifetime;
+
+use avian2d::prelude::*;
+use bevy::prelude::*;
+use serde::Deserialize;
+
+const AFFIXES_PATH: &str = "enemies/elites.affixes.ron";
+/// Size of the aura relative to the sprite of the elite.
+const AURA_SCALE: f32 = 1.4;
+const AURA_ALPHA: f32 = 0.5;
+/// Horizontal distance between enemies split off a dead elite.
+const SPLIT_SPACING: f32 = 0.5;
+const TRAIL_SIZE: Vec2 = Vec2::new(0.6, 0.2);
+const DEATH_EXPLOSION_IMPULSE: f32 = 4.0;
+
+/// Loads the affix table, so that it is ready before the game starts.
+pub fn load(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut preloaded: ResMut<PreloadedAssets>,
+) {
+    let handle = asset_server.load::<AffixTable>(AFFIXES_PATH);
+    preloaded.0.push(handle.clone().untyped());
+    commands.insert_resource(AffixTableHandle(handle));
+}
+/// Gives some of the newly spawned enemies random affixes of the table, which make them elites.
+pub fn roll(
+    mut commands: Commands,
+    mut rngs: ResMut<RunRng>,
+    table: Res<AffixTableHandle>,
+    tables: Res<Assets<AffixTable>>,
+    mut q_enemies: Query<
+        (
+            Entity,
+            &Name,
+            &Sprite,
+            &mut Health,
+            &mut MaxHealth,
+            &mut ContactDamage,
+        ),
+        (Added<Archetype>, Without<Common>),
+    >,
+) -> Result {
+    let table = tables.get(&table.0).ok_or("Affix table is not loaded")?;
+    q_enemies.iter_mut().for_each(
+        |(entity, name, sprite, mut health, mut max_health, mut contact_damage)| {
+            if table.affixes.is_empty() || !rngs.elite.chance(table.elite_chance) {
+                return;
+            }
+            let count = 1 + rngs.elite.next_u64() as usize
+                % table.max_affixes.clamp(1, table.affixes.len());
+            let mut candidates = (0..table.affixes.len()).collect::<Vec<_>>();
+            let affixes = (0..count)
+                .map(|_| {
+                    let i = candidates.remove(rngs.elite.next_u64() as usize % candidates.len());
+                    &table.affixes[i]
+                })
+                .collect::<Vec<_>>();
+
+            health.0 *= table.health_multiplier;
+            max_health.0 *= table.health_multiplier;
+            contact_damage.0 = contact_damage.0.scaled(table.damage_multiplier);
+            let mut elite = Elite {
+                speed: 1.0,
+                damage: table.damage_multiplier,
+                effects: Vec::new(),
+                trail: None,
+            };
+            let mut shield = 0.0;
+            affixes
+                .iter()
+                .flat_map(|affix| affix.effects.iter())
+                .for_each(|effect| {
+                    match *effect {
+                        AffixEffect::Speed(factor) => elite.speed *= factor,
+                        AffixEffect::Shield(amount) => shield += amount,
+                        AffixEffect::HazardTrail { interval, .. } => {
+                            let timer = Timer::from_seconds(interval, TimerMode::Repeating);
+                            elite.trail = Some(timer);
+                        }
+                        // Happen on death, see `on_death`.
+                        AffixEffect::ExplodeOnDeath { .. } | AffixEffect::SplitOnDeath { .. } => {}
+                    }
+                    elite.effects.push(*effect);
+                });
+
+            let names = affixes
+                .iter()
+                .map(|affix| affix.name.as_str())
+                .collect::<Vec<_>>();
+            let size = sprite.custom_size.unwrap_or(Vec2::ONE);
+            let mut entity = commands.entity(entity);
+            entity
+                .insert((Name::new(format!("{} {}", names.join(" "), name)), elite))
+                .with_child((
+                    Name::new("Aura"),
+                    Sprite::from_color(
+                        affixes[0].color().with_alpha(AURA_ALPHA),
+                        AURA_SCALE * size,
+                    ),
+                    Transform::from_translation(-0.1 * Vec3::Z),
+                ));
+            if shield > 0.0 {
+                entity.insert(Shield::new(shield));
+            }
+        },
+    );
+
+    Ok(())
+}
+/// Leaves hazards behind elites with a hazard trail.
+pub fn trail(
+    time_fixed: Res<Time>,
+    mut commands: Commands,
+    mut q_elites: Query<(&mut Elite, &GlobalTransform, &Sprite)>,
+) {
+    let dt = time_fixed.delta();
+    q_elites
+        .iter_mut()
+        .for_each(|(mut elite, transform, sprite)| {
+            let Some(timer) = elite.trail.as_mut() else {
+                return;
+            };
+            if !timer.tick(dt).just_finished() {
+                return;
+            }
+            let height = sprite.custom_size.unwrap_or(Vec2::ONE).y;
+            let feet = transform.translation() - 0.5 * height * Vec3::Y;
+            elite.effects.iter().for_each(|effect| {
+                if let AffixEffect::HazardTrail { kind, lifetime, .. } = *effect {
+                    // Trails hurt only the player and its allies.
+                    commands
+                        .spawn((
+                            hazard(kind, feet, TRAIL_SIZE),
+                            Lifetime::new(lifetime),
+                            DespawnOnExit(MainState::Game),
+                        ))
+                        .insert(CollisionLayers::new(
+                            CollisionGroup::Hazard,
+                            [CollisionGroup::Player, CollisionGroup::Summon],
+                        ));
+                }
+            });
+        });
+}
+/// Performs the death effects of elites and drops loot of dying enemies, before
+/// `Health::system` despawns them.
+pub fn on_death(
+    mut commands: Commands,
+    mut rngs: ResMut<RunRng>,
+    asset_server: Res<AssetServer>,
+    table: Res<AffixTableHandle>,
+    tables: Res<Assets<AffixTable>>,
+    q_enemies: Query<(Entity, &Health, &GlobalTransform, Option<&Elite>), With<Archetype>>,
+    q_stage: Query<(Entity, &GlobalTransform), With<Stage>>,
+    mut explosion_messages: MessageWriter<Explosion>,
+) -> Result {
+    let table = tables.get(&table.0).ok_or("Affix table is not loaded")?;
+    let (stage, stage_transform) = q_stage.single()?;
+    q_enemies
+        .iter()
+        .filter(|(_, health, _, _)| health.0 <= 0.0)
+        .for_each(|(entity, _, transform, elite)| {
+            let position = transform.translation();
+            elite
+                .into_iter()
+                .flat_map(|elite| elite.effects.iter())
+                .for_each(|effect| match *effect {
+                    AffixEffect::ExplodeOnDeath { radius, damage } => {
+                        explosion_messages.write(Explosion {
+                            source: entity,
+                            position: position.xy(),
+                            radius,
+                            damage: Damage::new(damage, DamageType::Explosive),
+                            impulse: DEATH_EXPLOSION_IMPULSE,
+                            status: None,
+                            hostile: true,
+                        });
+                    }
+                    AffixEffect::SplitOnDeath { archetype, count } => {
+                        (0..count).for_each(|i| {
+                            let offset = SPLIT_SPACING * (i as f32 - (count - 1) as f32 / 2.0);
+                            spawn_enemy(&mut commands, archetype, position + offset * Vec3::X)
+                                .insert(Common);
+                        });
+                    }
+                    AffixEffect::Speed(_)
+                    | AffixEffect::Shield(_)
+                    | AffixEffect::HazardTrail { .. } => {}
+                });
+
+            let drop_chance = match elite {
+                Some(_) => table.elite_drop_chance,
+                None => table.drop_chance,
+            };
+            if rngs.loot.chance(drop_chance) {
+                let loot = Item::ALL[rngs.loot.next_u64() as usize % Item::ALL.len()].clone();
+                commands.spawn((
+                    item(
+                        loot,
+                        &asset_server,
+                        position - stage_transform.translation(),
+                    ),
+                    ChildOf(stage),
+                ));
+            }
+        });
+
+    Ok(())
+}
+
+/// Loaded from `assets/enemies/elites.affixes.ron`.
+#[derive(Asset, TypePath, Deserialize)]
+pub struct AffixTable {
+    /// Chance of a spawned enemy to become an elite.
+    pub elite_chance: f32,
+    /// Most affixes one elite rolls.
+    pub max_affixes: usize,
+    pub health_multiplier: f32,
+    pub damage_multiplier: f32,
+    /// Chance of a common enemy to drop an item.
+    pub drop_chance: f32,
+    pub elite_drop_chance: f32,
+    pub affixes: Vec<Affix>,
+}
+
+#[derive(Resource)]
+pub struct AffixTableHandle(Handle<AffixTable>);
+
+#[derive(Deserialize)]
+pub struct Affix {
+    /// Prefixed to the name of the elite.
+    pub name: String,
+    /// Color of the aura, in sRGB.
+    color: (f32, f32, f32),
+    pub effects: Vec<AffixEffect>,
+}
+impl Affix {
+    fn color(&self) -> Color {
+        Color::srgb(self.color.0, self.color.1, self.color.2)
+    }
+}
+
+/// What an affix does. Affixes combine any number of these.
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub enum AffixEffect {
+    /// Multiplies the movement speed.
+    Speed(f32),
+    /// Shield absorbing damage before health.
+    Shield(f32),
+    /// Explosion hurting the player on death.
+    ExplodeOnDeath { radius: f32, damage: f32 },
+    /// Enemies spawned on death.
+    SplitOnDeath { archetype: Archetype, count: u32 },
+    /// Hazard left behind every `interval` seconds, which lasts `lifetime` seconds.
+    HazardTrail {
+        kind: HazardKind,
+        interval: f32,
+        lifetime: f32,
+    },
+}
+
+/// Enemy with affixes.
+#[derive(Component)]
+pub struct Elite {
+    /// Multiplier of the movement speed.
+    pub speed: f32,
+    /// Multiplier of the damage of attacks.
+    pub damage: f32,
+    effects: Vec<AffixEffect>,
+    trail: Option<Timer>,
+}
+
+/// Enemy that never becomes an elite, e.g. one split off an elite.
+#[derive(Component)]
+pub struct Common;
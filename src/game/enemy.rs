@@ -1,74 +1,235 @@
 use super::CollisionGroup;
 use super::Enemy;
-use super::EnemySpawner;
 use super::Health;
 use super::MainState;
-use super::Player;
 use super::Projectile;
-use super::SPRITE_ORIENTATION;
+use super::ai::Behaviour;
+use super::ai::telegraph_marker;
+use super::ballistics::ProjectileModifiers;
+use super::ballistics::split;
+use super::damage::ContactDamage;
+use super::damage::Damage;
+use super::damage::DamageFalloff;
+use super::damage::DamageType;
+use super::damage::Hit;
+use super::damage::Resistances;
+use super::nav::NavPath;
+use super::platformer::ground_caster;
+use super::status::StatusEffects;
 
 use avian2d::prelude::*;
 use bevy::prelude::*;
+use serde::Deserialize;
 
-const ENEMY_SPEED: f32 = 4.0;
-const ENEMY_SIZE: f32 = 0.4;
-const ENEMY_HEALTH: f32 = 3.0;
+/// Impulse with which projectiles push enemies along their flight.
+const PROJECTILE_KNOCKBACK: f32 = 0.5;
+const PROJECTILE_HITSTUN: f32 = 0.2;
 
-pub fn spawn(
-    time: Res<Time>,
+/// Spawns an enemy of the archetype, together with the components of its behaviour.
+pub fn spawn_enemy<'a>(
+    commands: &'a mut Commands,
+    archetype: Archetype,
+    position: Vec3,
+) -> EntityCommands<'a> {
+    let size = archetype.size();
+    let mut filters = vec![
+        CollisionGroup::Player,
+        CollisionGroup::Projectile,
+        CollisionGroup::Summon,
+        CollisionGroup::Hazard,
+    ];
+    if !archetype.flying() {
+        filters.push(CollisionGroup::Terrain);
+    }
+    let mut enemy = commands.spawn((
+        Name::new(archetype.name()),
+        Enemy,
+        archetype,
+        Health(archetype.health()),
+        MaxHealth(archetype.health()),
+        Resistances::default(),
+        StatusEffects::default(),
+        ContactDamage(Damage::new(
+            archetype.contact_damage(),
+            DamageType::Slashing,
+        )),
+        Transform::from_translation(position),
+        Sprite::from_color(archetype.color(), size),
+        (
+            RigidBody::Dynamic,
+            GravityScale(if archetype.flying() { 0.0 } else { 1.0 }),
+            LockedAxes::new().lock_rotation(),
+            Collider::rectangle(size.x, size.y),
+            CollisionLayers::new(CollisionGroup::Enemy, filters),
+        ),
+        DespawnOnExit(MainState::Game),
+        Behaviour::new(position.xy()),
+    ));
+    enemy.with_child(telegraph_marker(size));
+    if !archetype.flying() {
+        enemy.insert((NavPath::default(), ground_caster(size.x, size.y)));
+    }
+    enemy
+}
+// TODO Has non-zero probability of interaction with despawned entity.
+/// Damages enemies touched by projectiles. A projectile is despawned on its hit, unless it can
+/// still pierce, and never hits the same enemy twice.
+pub fn hit(
     mut commands: Commands,
-    mut q_spawners: Query<(&GlobalTransform, &mut EnemySpawner)>,
+    q_enemies: Query<Entity, With<Enemy>>,
+    mut q_projectiles: Query<(
+        Entity,
+        &mut Projectile,
+        &GlobalTransform,
+        &LinearVelocity,
+        Option<&DamageFalloff>,
+        Option<&mut ProjectileModifiers>,
+    )>,
+    collisions: Collisions,
+    mut hit_messages: MessageWriter<Hit>,
 ) {
-    q_spawners.iter_mut().for_each(|(transform, mut timer)| {
-        if timer.0.tick(time.delta()).is_finished() {
-            commands.spawn((
-                Name::new("Enemy"),
-                Enemy,
-                Health(ENEMY_HEALTH),
-                RigidBody::Dynamic,
-                Transform::from_translation(transform.translation()),
-                Sprite::from_color(Color::srgb(1.0, 0.0, 0.6), Vec2::splat(ENEMY_SIZE)),
-                Collider::rectangle(ENEMY_SIZE, ENEMY_SIZE),
-                CollisionLayers::new(
-                    CollisionGroup::Enemy,
-                    [CollisionGroup::Player, CollisionGroup::Projectile],
-                ),
-                DespawnOnExit(MainState::Game),
-            ));
-        }
-    });
+    q_projectiles.iter_mut().for_each(
+        |(entity, mut projectile, transform, velocity, falloff, mut modifiers)| {
+            for colliding_entity in collisions.entities_colliding_with(entity) {
+                if !q_enemies.contains(colliding_entity)
+                    || projectile.hit.contains(&colliding_entity)
+                {
+                    continue;
+                }
+                let damage = match falloff {
+                    Some(falloff) => falloff.apply(projectile.damage, transform.translation().xy()),
+                    None => projectile.damage,
+                };
+                hit_messages.write(
+                    Hit::new(projectile.source, colliding_entity, damage).with_knockback(
+                        PROJECTILE_KNOCKBACK * velocity.normalize_or_zero(),
+                        PROJECTILE_HITSTUN,
+                    ),
+                );
+                projectile.hit.push(colliding_entity);
+
+                let Some(modifiers) = modifiers.as_deref_mut() else {
+                    commands.entity(entity).despawn();
+                    break;
+                };
+                if modifiers.split > 0 {
+                    split(
+                        &mut commands,
+                        &projectile,
+                        modifiers,
+                        transform.translation(),
+                        velocity.0,
+                    );
+                    modifiers.split = 0;
+                }
+                if modifiers.pierce > 0 {
+                    modifiers.pierce -= 1;
+                } else {
+                    commands.entity(entity).despawn();
+                    break;
+                }
+            }
+        },
+    )
 }
-pub fn handle_state(
-    mut q_enemies: Query<(&GlobalTransform, &mut Transform, &mut LinearVelocity), With<Enemy>>,
-    q_player: Query<&GlobalTransform, (With<Player>, Without<Enemy>)>,
-) -> Result {
-    let player_pos = q_player.single()?.translation();
+/// Tints enemies with the color of their most recent status effect.
+pub fn tint(
+    mut q_enemies: Query<
+        (&mut Sprite, &StatusEffects, &Archetype),
+        (With<Enemy>, Changed<StatusEffects>),
+    >,
+) {
     q_enemies
         .iter_mut()
-        .for_each(|(global_transform, mut transform, mut v)| {
-            let towards_player = (player_pos - global_transform.translation())
-                .xy()
-                .normalize();
-            transform.rotation = Quat::from_rotation_arc_2d(SPRITE_ORIENTATION, towards_player);
-            v.0 = ENEMY_SPEED * towards_player;
+        .for_each(|(mut sprite, effects, archetype)| {
+            sprite.color = effects.tint().unwrap_or(archetype.color());
         });
+}
 
-    Ok(())
+/// Kind of enemy, which decides its stats, looks and behaviour.
+#[derive(Component, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Archetype {
+    /// Walks straight at the player.
+    Grunt,
+    /// Keeps its distance and shoots.
+    Ranged,
+    /// Winds up, then dashes at the player.
+    Charger,
+    /// Slow, big and hard to kill.
+    Tank,
+    /// Ignores gravity and the terrain.
+    Flyer,
 }
-// TODO Has non-zero probability of interaction with despawned entity.
-pub fn hit(
-    mut commands: Commands,
-    mut q_enemies: Query<&mut Health, With<Enemy>>,
-    q_projectiles: Query<Entity, With<Projectile>>,
-    collisions: Collisions,
-) {
-    q_projectiles.iter().for_each(|projectile| {
-        for colliding_entity in collisions.entities_colliding_with(projectile) {
-            if let Ok(mut health) = q_enemies.get_mut(colliding_entity) {
-                health.0 -= 1.0;
-                commands.entity(projectile).despawn();
-                break;
-            }
+impl Archetype {
+    pub const ALL: [Archetype; 5] = [
+        Self::Grunt,
+        Self::Ranged,
+        Self::Charger,
+        Self::Tank,
+        Self::Flyer,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Grunt => "Grunt",
+            Self::Ranged => "Ranged Enemy",
+            Self::Charger => "Charger",
+            Self::Tank => "Tank",
+            Self::Flyer => "Flyer",
+        }
+    }
+    pub fn health(&self) -> f32 {
+        match self {
+            Self::Grunt => 3.0,
+            Self::Ranged => 2.0,
+            Self::Charger => 4.0,
+            Self::Tank => 15.0,
+            Self::Flyer => 2.0,
         }
-    })
+    }
+    /// Size of the sprite and the collider.
+    fn size(&self) -> Vec2 {
+        match self {
+            Self::Grunt => Vec2::splat(0.4),
+            Self::Ranged => Vec2::new(0.35, 0.6),
+            Self::Charger => Vec2::new(0.6, 0.45),
+            Self::Tank => Vec2::splat(0.9),
+            Self::Flyer => Vec2::new(0.4, 0.25),
+        }
+    }
+    fn color(&self) -> Color {
+        match self {
+            Self::Grunt => Color::srgb(1.0, 0.0, 0.6),
+            Self::Ranged => Color::srgb(0.2, 0.8, 0.3),
+            Self::Charger => Color::srgb(0.9, 0.3, 0.1),
+            Self::Tank => Color::srgb(0.4, 0.2, 0.6),
+            Self::Flyer => Color::srgb(0.3, 0.6, 1.0),
+        }
+    }
+    /// Movement speed in meters per second.
+    pub fn speed(&self) -> f32 {
+        match self {
+            Self::Grunt => 3.0,
+            Self::Ranged => 2.0,
+            Self::Charger => 2.0,
+            Self::Tank => 1.0,
+            Self::Flyer => 3.5,
+        }
+    }
+    fn contact_damage(&self) -> f32 {
+        match self {
+            Self::Grunt => 10.0,
+            Self::Ranged => 5.0,
+            Self::Charger => 20.0,
+            Self::Tank => 25.0,
+            Self::Flyer => 8.0,
+        }
+    }
+    pub fn flying(&self) -> bool {
+        matches!(self, Self::Flyer)
+    }
 }
+
+/// Health an enemy spawned with, including the multiplier of elites.
+#[derive(Component)]
+pub struct MaxHealth(pub f32);
@@ -0,0 +1,133 @@
+use super::CollisionGroup;
+use super::Enemy;
+use super::Health;
+use super::damage::CRIT_COLOR;
+use super::damage::Damage;
+use super::damage::DamageFalloff;
+use super::damage::Hit;
+use super::status::Inflict;
+use super::status::Status;
+use crate::MainState;
+use crate::utils::Lifetime;
+
+use avian2d::prelude::*;
+use bevy::prelude::*;
+
+const EXPLOSION_VISUAL_TIME: f32 = 0.15;
+const EXPLOSION_HITSTUN: f32 = 0.4;
+
+/// Explodes entities whose `Fuse` burned out or which touched an enemy.
+pub fn detonate(
+    time_fixed: Res<Time>,
+    mut commands: Commands,
+    mut q_fuses: Query<(Entity, &mut Fuse, &GlobalTransform, &CollidingEntities)>,
+    q_enemies: Query<(), With<Enemy>>,
+    mut explosion_messages: MessageWriter<Explosion>,
+) {
+    let dt = time_fixed.delta();
+    q_fuses
+        .iter_mut()
+        .for_each(|(entity, mut fuse, transform, colliding_entities)| {
+            let burned_out = fuse.timer.tick(dt).is_finished();
+            let touched_enemy = colliding_entities.iter().any(|e| q_enemies.contains(*e));
+            if burned_out || touched_enemy {
+                explosion_messages.write(Explosion {
+                    position: transform.translation().xy(),
+                    ..fuse.explosion
+                });
+                commands.entity(entity).despawn();
+            }
+        });
+}
+/// Damages and pushes away every enemy within the radius, both weakening with the distance.
+/// Hostile explosions hit the player and its summons instead.
+pub fn explode(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    spatial_query: SpatialQuery,
+    mut explosion_messages: MessageReader<Explosion>,
+    q_targets: Query<&GlobalTransform, With<Health>>,
+    mut hit_messages: MessageWriter<Hit>,
+    mut inflict_messages: MessageWriter<Inflict>,
+) {
+    explosion_messages.read().for_each(|explosion| {
+        let falloff = DamageFalloff {
+            origin: explosion.position,
+            range: explosion.radius,
+        };
+        spatial_query
+            .shape_intersections(
+                &Collider::circle(explosion.radius),
+                explosion.position,
+                0.0,
+                &SpatialQueryFilter::from_mask(if explosion.hostile {
+                    LayerMask::from([CollisionGroup::Player, CollisionGroup::Summon])
+                } else {
+                    LayerMask::from(CollisionGroup::Enemy)
+                }),
+            )
+            .into_iter()
+            .for_each(|entity| {
+                let Ok(transform) = q_targets.get(entity) else {
+                    return;
+                };
+                let position = transform.translation().xy();
+                let knockback = falloff.fraction(position)
+                    * explosion.impulse
+                    * (position - explosion.position).normalize_or_zero();
+                hit_messages.write(
+                    Hit::new(
+                        explosion.source,
+                        entity,
+                        falloff.apply(explosion.damage, position),
+                    )
+                    .with_knockback(knockback, EXPLOSION_HITSTUN),
+                );
+                if let Some(status) = explosion.status {
+                    inflict_messages.write(Inflict {
+                        source: explosion.source,
+                        target: entity,
+                        status,
+                    });
+                }
+            });
+
+        commands.spawn((
+            Name::new("Explosion"),
+            Mesh2d(meshes.add(Circle::new(explosion.radius))),
+            MeshMaterial2d(materials.add(if explosion.damage.critical {
+                CRIT_COLOR.with_alpha(0.5)
+            } else {
+                Color::srgba(1.0, 0.6, 0.1, 0.5)
+            })),
+            Transform::from_translation(explosion.position.extend(0.5)),
+            Lifetime::new(EXPLOSION_VISUAL_TIME),
+            DespawnOnExit(MainState::Game),
+        ));
+    });
+}
+
+#[derive(Message, Clone, Copy)]
+pub struct Explosion {
+    /// Entity responsible for the explosion.
+    pub source: Entity,
+    pub position: Vec2,
+    pub radius: f32,
+    /// Damage in the center of the explosion.
+    pub damage: Damage,
+    /// Impulse in the center of the explosion.
+    pub impulse: f32,
+    /// Inflicted on everything caught in the explosion.
+    pub status: Option<Status>,
+    /// Caused by an enemy, so it hits the player and its summons instead of enemies.
+    pub hostile: bool,
+}
+
+/// Explosion that will happen when the timer finishes or when the entity touches an enemy.
+#[derive(Component)]
+pub struct Fuse {
+    pub timer: Timer,
+    /// Position is overwritten with the position of the entity.
+    pub explosion: Explosion,
+}
@@ -0,0 +1,235 @@
+use super::CollisionGroup;
+use super::Health;
+use super::Player;
+use super::damage::Damage;
+use super::damage::DamageType;
+use super::damage::Hit;
+use super::status::Inflict;
+use super::status::Status;
+use super::status::StatusKind;
+
+use avian2d::prelude::*;
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+const SWITCH_SIZE: Vec2 = Vec2::new(0.3, 0.6);
+const INTERACTION_DISTANCE: f32 = 1.0;
+
+/// Hazard of a given kind, placed by a stage definition.
+pub fn hazard(kind: HazardKind, position: Vec3, size: Vec2) -> impl Bundle {
+    (
+        Name::new(format!("{:?}", kind)),
+        Hazard {
+            active: true,
+            power: kind.power(),
+        },
+        kind,
+        HazardTicks::default(),
+        Sprite::from_color(kind.color(), size),
+        Transform::from_translation(position),
+        RigidBody::Static,
+        Collider::rectangle(size.x, size.y),
+        Sensor,
+        CollidingEntities::default(),
+        CollisionLayers::new(
+            CollisionGroup::Hazard,
+            [
+                CollisionGroup::Player,
+                CollisionGroup::Enemy,
+                CollisionGroup::Summon,
+            ],
+        ),
+    )
+}
+/// Switch that toggles given hazards when the player interacts with it.
+pub fn switch(hazards: Vec<Entity>, position: Vec3) -> impl Bundle {
+    (
+        Name::new("Switch"),
+        Switch(hazards),
+        Sprite::from_color(Color::srgb(0.8, 0.8, 0.2), SWITCH_SIZE),
+        Transform::from_translation(position),
+    )
+}
+
+/// Damages every entity with `Health` overlapping an active hazard and inflicts its status,
+/// once when it enters and then every `tick_interval` for as long as it stays.
+pub fn damage(
+    time_fixed: Res<Time>,
+    mut q_hazards: Query<(
+        Entity,
+        &Hazard,
+        &HazardKind,
+        &mut HazardTicks,
+        &CollidingEntities,
+    )>,
+    q_targets: Query<(), With<Health>>,
+    mut hit_messages: MessageWriter<Hit>,
+    mut inflict_messages: MessageWriter<Inflict>,
+) {
+    let dt = time_fixed.delta();
+    q_hazards
+        .iter_mut()
+        .for_each(|(entity, hazard, kind, mut ticks, colliding_entities)| {
+            ticks
+                .0
+                .retain(|target, _| hazard.active && colliding_entities.contains(target));
+            if !hazard.active {
+                return;
+            }
+            colliding_entities
+                .iter()
+                .filter(|e| q_targets.contains(**e))
+                .for_each(|target| {
+                    let tick = match ticks.0.get_mut(target) {
+                        Some(timer) => timer.tick(dt).just_finished(),
+                        None => {
+                            ticks.0.insert(
+                                *target,
+                                Timer::from_seconds(kind.tick_interval(), TimerMode::Repeating),
+                            );
+                            true
+                        }
+                    };
+                    if tick {
+                        // Ticks never grant i-frames, which would outlast the tick interval.
+                        hit_messages.write(
+                            Hit::new(
+                                entity,
+                                *target,
+                                Damage::new(hazard.power, kind.damage_type()),
+                            )
+                            .periodic(),
+                        );
+                        inflict_messages.write(Inflict {
+                            source: entity,
+                            target: *target,
+                            status: kind.status(),
+                        });
+                    }
+                });
+        });
+}
+pub fn cycle(time_fixed: Res<Time>, mut q_hazards: Query<(&mut Hazard, &mut HazardCycle)>) {
+    let dt = time_fixed.delta();
+    q_hazards.iter_mut().for_each(|(mut hazard, mut cycle)| {
+        if cycle.0.tick(dt).just_finished() {
+            hazard.active = !hazard.active;
+        }
+    });
+}
+// TODO Maybe create common logic for general interaction, regardless of door/item/switch
+pub fn toggle_switches(
+    q_switches: Query<(&GlobalTransform, &Switch)>,
+    q_player: Query<&GlobalTransform, With<Player>>,
+    mut q_hazards: Query<&mut Hazard>,
+) -> Result {
+    let player_pos = q_player.single()?;
+
+    q_switches
+        .iter()
+        .filter(|(switch_pos, _)| {
+            player_pos.translation().distance(switch_pos.translation()) <= INTERACTION_DISTANCE
+        })
+        .for_each(|(_, switch)| {
+            let mut hazards = q_hazards.iter_many_mut(&switch.0);
+            while let Some(mut hazard) = hazards.fetch_next() {
+                hazard.active = !hazard.active;
+            }
+        });
+
+    Ok(())
+}
+/// Dims inactive hazards.
+pub fn visual_state(mut q_hazards: Query<(&mut Sprite, &Hazard, &HazardKind), Changed<Hazard>>) {
+    q_hazards.iter_mut().for_each(|(mut sprite, hazard, kind)| {
+        sprite.color = if hazard.active {
+            kind.color()
+        } else {
+            kind.color().darker(0.3)
+        };
+    });
+}
+
+/// Environmental damage dealt to everything overlapping the entity, while it is `active`.
+#[derive(Component)]
+pub struct Hazard {
+    pub active: bool,
+    /// Damage dealt per tick.
+    pub power: f32,
+}
+
+#[derive(Component, Deserialize, Clone, Copy, Debug)]
+pub enum HazardKind {
+    Spikes,
+    Lava,
+    ElectrifiedFloor,
+}
+impl HazardKind {
+    fn power(&self) -> f32 {
+        match self {
+            Self::Spikes => 10.0,
+            Self::Lava => 5.0,
+            Self::ElectrifiedFloor => 3.0,
+        }
+    }
+    /// Seconds between consecutive damage ticks on the same target.
+    fn tick_interval(&self) -> f32 {
+        match self {
+            Self::Spikes => 1.0,
+            Self::Lava => 0.5,
+            Self::ElectrifiedFloor => 0.25,
+        }
+    }
+    fn damage_type(&self) -> DamageType {
+        match self {
+            Self::Spikes => DamageType::Piercing,
+            Self::Lava => DamageType::Fire,
+            Self::ElectrifiedFloor => DamageType::Electric,
+        }
+    }
+    /// Status inflicted with every damage tick.
+    fn status(&self) -> Status {
+        match self {
+            Self::Spikes => Status {
+                kind: StatusKind::Bleed,
+                duration: 3.0,
+                potency: 2.0,
+            },
+            Self::Lava => Status {
+                kind: StatusKind::Burn,
+                duration: 2.0,
+                potency: 4.0,
+            },
+            Self::ElectrifiedFloor => Status {
+                kind: StatusKind::Slow,
+                duration: 1.0,
+                potency: 0.25,
+            },
+        }
+    }
+    fn color(&self) -> Color {
+        match self {
+            Self::Spikes => Color::srgb(0.6, 0.6, 0.6),
+            Self::Lava => Color::srgb(1.0, 0.35, 0.0),
+            Self::ElectrifiedFloor => Color::srgb(0.3, 0.6, 1.0),
+        }
+    }
+}
+
+/// Damage timers of every target currently overlapping the hazard.
+#[derive(Component, Default)]
+pub struct HazardTicks(HashMap<Entity, Timer>);
+
+/// Switches the hazard on and off every time the timer finishes.
+#[derive(Component)]
+pub struct HazardCycle(pub Timer);
+impl HazardCycle {
+    pub fn from_seconds(seconds: f32) -> Self {
+        HazardCycle(Timer::from_seconds(seconds, TimerMode::Repeating))
+    }
+}
+
+/// Toggles the hazards when interacted with.
+#[derive(Component)]
+pub struct Switch(Vec<Entity>);
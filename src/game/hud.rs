@@ -3,11 +3,30 @@ use super::Health;
 use super::Player;
 use super::Run;
 use super::Stats;
+use super::boss::Boss;
+use super::champion::SelectedDefinition;
+use super::damage::Shield;
+use super::skills::SkillSlots;
+use super::status::StatusEffects;
+use super::status::StatusKind;
 use crate::MainState;
 
 use bevy::prelude::*;
 
-pub fn spawn(mut commands: Commands, asset_server: Res<AssetServer>) {
+const HEALTH_BAR_WIDTH: f32 = 240.0;
+const HEALTH_BAR_HEIGHT: f32 = 16.0;
+const HEALTH_COLOR: Color = Color::srgb(0.2, 0.8, 0.2);
+const SHIELD_COLOR: Color = Color::srgb(0.5, 0.8, 1.0);
+const BOSS_BAR_WIDTH: f32 = 480.0;
+const BOSS_HEALTH_COLOR: Color = Color::srgb(0.8, 0.1, 0.1);
+
+pub fn spawn(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    champion: SelectedDefinition,
+) -> Result {
+    let skills = &champion.get()?.skills;
+
     commands
         .spawn((
             Name::new("Hud"),
@@ -33,6 +52,35 @@ pub fn spawn(mut commands: Commands, asset_server: Res<AssetServer>) {
                 })
                 .with_children(|parent| {
                     parent.spawn((Node::default(), EquipmentNode));
+                    parent
+                        .spawn((
+                            Node {
+                                flex_direction: FlexDirection::Column,
+                                align_items: AlignItems::Center,
+                                display: Display::None,
+                                ..Default::default()
+                            },
+                            BossHud,
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn((Text::default(), BossName));
+                            parent
+                                .spawn((
+                                    Node {
+                                        width: Val::Px(BOSS_BAR_WIDTH),
+                                        height: Val::Px(HEALTH_BAR_HEIGHT),
+                                        ..Default::default()
+                                    },
+                                    BackgroundColor(Color::srgb(0.15, 0.15, 0.15)),
+                                ))
+                                .with_children(|parent| {
+                                    parent.spawn((
+                                        Node::default(),
+                                        BackgroundColor(BOSS_HEALTH_COLOR),
+                                        BossBar,
+                                    ));
+                                });
+                        });
                     parent.spawn((Text::default(), RunTime));
                 });
             parent
@@ -44,33 +92,154 @@ pub fn spawn(mut commands: Commands, asset_server: Res<AssetServer>) {
                     ..Default::default()
                 })
                 .with_children(|parent| {
-                    parent.spawn((Text::default(), HealthHud));
                     parent
-                        .spawn((Node::default(), Skills))
+                        .spawn(Node {
+                            flex_direction: FlexDirection::Column,
+                            ..Default::default()
+                        })
                         .with_children(|parent| {
-                            // TODO Generalise depending on character. Maybe use spritesheet?
                             parent
-                                .spawn((ImageNode::from(asset_server.load("skill_a.png")), SkillA));
-                            parent
-                                .spawn((ImageNode::from(asset_server.load("skill_b.png")), SkillB));
-                            parent
-                                .spawn((ImageNode::from(asset_server.load("skill_c.png")), SkillC));
-                            parent
-                                .spawn((ImageNode::from(asset_server.load("skill_d.png")), SkillD));
+                                .spawn((
+                                    Node {
+                                        width: Val::Px(HEALTH_BAR_WIDTH),
+                                        height: Val::Px(HEALTH_BAR_HEIGHT),
+                                        flex_direction: FlexDirection::Row,
+                                        ..Default::default()
+                                    },
+                                    BackgroundColor(Color::srgb(0.15, 0.15, 0.15)),
+                                ))
+                                .with_children(|parent| {
+                                    parent.spawn((
+                                        Node::default(),
+                                        BackgroundColor(HEALTH_COLOR),
+                                        HealthBar,
+                                    ));
+                                    parent.spawn((
+                                        Node::default(),
+                                        BackgroundColor(SHIELD_COLOR),
+                                        ShieldBar,
+                                    ));
+                                });
+                            parent.spawn((Text::default(), HealthHud));
+                        });
+                    parent.spawn(Node::default()).with_children(|parent| {
+                        StatusKind::ALL.iter().for_each(|kind| {
+                            parent.spawn((
+                                ImageNode::from(asset_server.load(kind.icon())),
+                                Node {
+                                    display: Display::None,
+                                    ..Default::default()
+                                },
+                                StatusIcon(*kind),
+                            ));
+                        });
+                    });
+                    parent
+                        .spawn((Node::default(), Skills))
+                        .with_children(|parent| {
+                            // TODO Maybe use spritesheet?
+                            skills.iter().enumerate().for_each(|(slot, skill)| {
+                                parent
+                                    .spawn(Node {
+                                        flex_direction: FlexDirection::Column,
+                                        align_items: AlignItems::Center,
+                                        ..Default::default()
+                                    })
+                                    .with_children(|parent| {
+                                        parent
+                                            .spawn(ImageNode::from(asset_server.load(&skill.icon)));
+                                        parent.spawn((Text::default(), SkillCooldown(slot)));
+                                    });
+                            });
                         });
                 });
         });
+
+    Ok(())
 }
 
+/// Shows health and shield as consecutive segments of one bar, sized relative to their maxima.
 pub fn update_health(
     mut q_health_hud: Query<&mut Text, With<HealthHud>>,
-    q_player: Query<(&Health, &Stats), With<Player>>,
+    mut q_health_bar: Query<&mut Node, (With<HealthBar>, Without<ShieldBar>)>,
+    mut q_shield_bar: Query<&mut Node, (With<ShieldBar>, Without<HealthBar>)>,
+    q_player: Query<(&Health, &Shield, &Stats), With<Player>>,
+) -> Result {
+    let (health, shield, stats) = q_player.single()?;
+    let total = (stats.max_health + stats.shield).max(1.0);
+    q_health_bar.single_mut()?.width = Val::Percent(100.0 * health.0.max(0.0) / total);
+    q_shield_bar.single_mut()?.width = Val::Percent(100.0 * shield.amount / total);
+    q_health_hud.single_mut()?.0 = if stats.shield > 0.0 {
+        format!(
+            "{:.0}/{:.0} +{:.0}",
+            health.0, stats.max_health, shield.amount
+        )
+    } else {
+        format!("{:.0}/{:.0}", health.0, stats.max_health)
+    };
+    Ok(())
+}
+
+/// Shows the name and health of the boss, while there is one.
+pub fn update_boss(
+    mut q_boss_hud: Query<&mut Node, (With<BossHud>, Without<BossBar>)>,
+    mut q_boss_bar: Query<&mut Node, (With<BossBar>, Without<BossHud>)>,
+    mut q_boss_name: Query<&mut Text, With<BossName>>,
+    q_bosses: Query<(&Boss, &Health)>,
+) -> Result {
+    let mut boss_hud = q_boss_hud.single_mut()?;
+    let Some((boss, health)) = q_bosses.iter().next() else {
+        if boss_hud.display != Display::None {
+            boss_hud.display = Display::None;
+        }
+        return Ok(());
+    };
+    boss_hud.display = Display::Flex;
+    q_boss_bar.single_mut()?.width =
+        Val::Percent(100.0 * health.0.max(0.0) / boss.kind.max_health());
+    q_boss_name.single_mut()?.0 = boss.kind.name().to_string();
+    Ok(())
+}
+
+/// Shows effective cooldown of every skill, preceded by the time remaining until it is ready.
+pub fn update_cooldowns(
+    mut q_cooldowns: Query<(&mut Text, &SkillCooldown)>,
+    q_player: Query<(&SkillSlots, &Stats), With<Player>>,
 ) -> Result {
-    let (health, stats) = q_player.single()?;
-    q_health_hud.single_mut()?.0 = format!("{}/{}", health.0, stats.max_health);
+    let (slots, stats) = q_player.single()?;
+    q_cooldowns.iter_mut().for_each(|(mut text, cooldown)| {
+        let slot = &slots.0[cooldown.0];
+        let effective = slot.effective_cooldown(stats);
+        let remaining = slot.remaining_cooldown(stats);
+        text.0 = if remaining > 0.0 {
+            format!("{:.1}/{:.1}", remaining, effective)
+        } else {
+            format!("{:.1}", effective)
+        };
+    });
     Ok(())
 }
 
+/// Shows icons of the status effects currently affecting the player.
+pub fn update_statuses(
+    mut q_icons: Query<(&mut Node, &StatusIcon)>,
+    q_player: Query<&StatusEffects, (With<Player>, Changed<StatusEffects>)>,
+) {
+    let Ok(effects) = q_player.single() else {
+        return;
+    };
+    q_icons.iter_mut().for_each(|(mut node, icon)| {
+        let display = if effects.has(icon.0) {
+            Display::Flex
+        } else {
+            Display::None
+        };
+        if node.display != display {
+            node.display = display;
+        }
+    });
+}
+
 pub fn update_run_time(mut q_text: Query<&mut Text, With<RunTime>>, q_run: Query<&Run>) -> Result {
     let stopwatch = q_run.single()?;
     q_text.single_mut()?.0 = format!("{:.2}", stopwatch.0.elapsed_secs());
@@ -94,18 +263,27 @@ pub fn update_equipment(
 
 #[derive(Component)]
 struct Skills;
+/// Cooldown of the skill in the given slot.
 #[derive(Component)]
-struct SkillA;
-#[derive(Component)]
-struct SkillB;
+struct SkillCooldown(usize);
+/// Visible while the player is affected by the status.
 #[derive(Component)]
-struct SkillC;
-#[derive(Component)]
-struct SkillD;
+struct StatusIcon(StatusKind);
 
 #[derive(Component)]
 pub struct HealthHud;
 #[derive(Component)]
+struct HealthBar;
+#[derive(Component)]
+struct ShieldBar;
+#[derive(Component)]
 pub struct RunTime;
+/// Name and health bar of the boss, hidden without one.
+#[derive(Component)]
+struct BossHud;
+#[derive(Component)]
+struct BossName;
+#[derive(Component)]
+struct BossBar;
 #[derive(Component)]
 pub struct EquipmentNode;
@@ -1,13 +1,30 @@
+use super::CollisionGroup;
 use super::Equipment;
 use super::Item;
 use super::ItemPickup;
 use super::Player;
+use crate::utils;
 
+use avian2d::prelude::*;
 use bevy::prelude::*;
 use std::cmp::Ordering;
 
 const INTERACTION_DISTANCE: f32 = 1.0;
 
+/// Item lying in the stage, waiting to be picked up.
+pub fn item(item: Item, asset_server: &Res<AssetServer>, position: Vec3) -> impl Bundle {
+    let size = item.size();
+    (
+        utils::image_size_to_sprite(item.image(asset_server), size),
+        item,
+        RigidBody::Dynamic,
+        Collider::rectangle(size.x, size.y),
+        CollisionLayers::new(CollisionGroup::Default, CollisionGroup::Terrain),
+        Mass(100.0),
+        Transform::from_translation(position),
+    )
+}
+
 // TODO In case of many interactables later in the game development pressing E might cause stagger,
 // which might be suboptimal. Consider not analysing all interactables when button pressed, but rather
 // periodically (doesn't have to be each frame) keep track of all entities that are potentially
@@ -0,0 +1,436 @@
+use super::CollisionGroup;
+use super::Enemy;
+use super::Player;
+use super::ai::AiState;
+use super::ai::Behaviour;
+use super::stages::Stage;
+
+use avian2d::prelude::*;
+use bevy::prelude::*;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::collections::VecDeque;
+
+/// Horizontal distance between two nodes of the same surface.
+const NODE_SPACING: f32 = 1.0;
+/// Highest surface above the current one an enemy jumps to.
+const MAX_JUMP_HEIGHT: f32 = 2.0;
+/// Widest horizontal distance an enemy jumps over.
+const MAX_JUMP_DISTANCE: f32 = 3.0;
+/// Widest horizontal distance from an edge to the node an enemy drops to.
+const MAX_DROP_DISTANCE: f32 = 2.0;
+/// Narrowest horizontal distance from an edge to the node an enemy drops to. Half the width of
+/// the widest ground enemy, which is then past the edge and falls off.
+const DROP_CLEARANCE: f32 = 0.5;
+/// Surfaces this much lower than the source of a link are dropped to, higher ones jumped to.
+const DROP_HEIGHT: f32 = 0.5;
+/// Extra cost of jumps, so that enemies walk when walking is about as long.
+const JUMP_COST: f32 = 2.0;
+/// Extra height enemies jump over the target surface, to clear its edge.
+const JUMP_CLEARANCE: f32 = 1.0;
+/// Waypoints closer than this horizontally count as reached.
+const ARRIVE_DISTANCE: f32 = 0.3;
+/// Highest an enemy's center stands above the node it is on.
+const STANDING_HEIGHT: f32 = 1.0;
+const REPATH_INTERVAL: f32 = 0.5;
+
+/// Rebuilds the `NavGraph` from the terrain colliders whenever a new stage was spawned.
+/// Runs after transform propagation, so that the terrain of the new stage is in place.
+pub fn build(
+    mut commands: Commands,
+    q_new_stages: Query<(), Added<Stage>>,
+    q_colliders: Query<(&Collider, &CollisionLayers, &GlobalTransform)>,
+) {
+    if q_new_stages.is_empty() {
+        return;
+    }
+    let terrain = q_colliders
+        .iter()
+        .filter(|(_, layers, _)| layers.memberships.has_all(CollisionGroup::Terrain))
+        .map(|(collider, _, transform)| {
+            let (_, rotation, translation) = transform.to_scale_rotation_translation();
+            let aabb = collider.aabb(translation.xy(), Rotation::from(rotation));
+            Rect::from_corners(aabb.min, aabb.max)
+        })
+        .collect::<Vec<_>>();
+    commands.insert_resource(NavGraph::from_terrain(&terrain));
+}
+/// Keeps the paths of chasing ground enemies toward the player up to date, and makes them jump
+/// whenever the next link of their path is a jump.
+pub fn follow(
+    time_fixed: Res<Time>,
+    graph: Res<NavGraph>,
+    gravity: Res<Gravity>,
+    mut q_enemies: Query<
+        (
+            &GlobalTransform,
+            &Behaviour,
+            &ShapeHits,
+            &mut LinearVelocity,
+            &mut NavPath,
+        ),
+        With<Enemy>,
+    >,
+    q_player: Query<&GlobalTransform, (With<Player>, Without<Enemy>)>,
+) -> Result {
+    let player_pos = q_player.single()?.translation().xy();
+    q_enemies.iter_mut().for_each(
+        |(transform, behaviour, ground_hits, mut velocity, mut path)| {
+            if behaviour.state != AiState::Chase {
+                path.waypoints.clear();
+                return;
+            }
+            let position = transform.translation().xy();
+            if path.repath.tick(time_fixed.delta()).just_finished() {
+                path.waypoints = graph.path(position, player_pos).unwrap_or_default();
+            }
+            while let Some(waypoint) = path.waypoints.front()
+                && waypoint.reached(position)
+            {
+                path.waypoints.pop_front();
+            }
+            if let Some(waypoint) = path.waypoints.front()
+                && waypoint.link == Link::Jump
+                && !ground_hits.is_empty()
+                && velocity.y <= 0.0
+            {
+                let height = (waypoint.position.y - position.y).max(0.0) + JUMP_CLEARANCE;
+                velocity.y = (2.0 * gravity.0.length() * height).sqrt();
+            }
+        },
+    );
+
+    Ok(())
+}
+
+/// How an enemy gets from one node to the next.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Link {
+    /// Along the same surface.
+    Walk,
+    /// Up to a higher surface, or over a gap.
+    Jump,
+    /// Off an edge, down to a lower surface.
+    Drop,
+}
+
+struct Node {
+    /// Top of the surface the node lies on.
+    position: Vec2,
+    surface: usize,
+    /// Bounding box of the surface.
+    rect: Rect,
+    /// Side of the surface over which the node is an edge: -1 left, 1 right, 0 both, `None` if
+    /// it is not an edge.
+    edge: Option<f32>,
+}
+
+/// Nodes on the walkable surfaces of the terrain, connected by walk, jump and drop links.
+#[derive(Resource, Default)]
+pub struct NavGraph {
+    nodes: Vec<Node>,
+    /// Outgoing links of every node, with the target node.
+    links: Vec<Vec<(usize, Link)>>,
+}
+impl NavGraph {
+    /// Builds the graph from the bounding boxes of the terrain. The top of every box is a
+    /// surface, except where another box covers it.
+    pub fn from_terrain(terrain: &[Rect]) -> Self {
+        let mut nodes = Vec::new();
+        terrain.iter().enumerate().for_each(|(surface, rect)| {
+            let count = (rect.width() / NODE_SPACING).floor().max(0.0) as usize + 1;
+            let spacing = rect.width() / count as f32;
+            let positions = (0..count)
+                .map(|i| Vec2::new(rect.min.x + spacing * (i as f32 + 0.5), rect.max.y))
+                .filter(|position| {
+                    let above = *position + 0.5 * ARRIVE_DISTANCE * Vec2::Y;
+                    !terrain.iter().any(|other| other.contains(above))
+                })
+                .collect::<Vec<_>>();
+            let last = positions.len().saturating_sub(1);
+            positions.iter().enumerate().for_each(|(i, position)| {
+                let edge = match (i == 0, i == last) {
+                    (true, true) => Some(0.0),
+                    (true, false) => Some(-1.0),
+                    (false, true) => Some(1.0),
+                    (false, false) => None,
+                };
+                nodes.push(Node {
+                    position: *position,
+                    surface,
+                    rect: *rect,
+                    edge,
+                });
+            });
+        });
+
+        let links = nodes
+            .iter()
+            .map(|from| {
+                nodes
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(to, node)| Some((to, Self::link(from, node, terrain)?)))
+                    .collect()
+            })
+            .collect();
+        NavGraph { nodes, links }
+    }
+    fn link(from: &Node, to: &Node, terrain: &[Rect]) -> Option<Link> {
+        let offset = to.position - from.position;
+        if offset == Vec2::ZERO {
+            return None;
+        }
+        if from.surface == to.surface {
+            return (offset.x.abs() <= 1.5 * NODE_SPACING).then_some(Link::Walk);
+        }
+        if offset.y > -DROP_HEIGHT {
+            // Terrain right above the takeoff would bump the head.
+            let blocked = terrain.iter().any(|rect| {
+                (rect.min.x..rect.max.x).contains(&from.position.x)
+                    && rect.min.y > from.position.y
+                    && rect.min.y < to.position.y + JUMP_CLEARANCE
+            });
+            return (!blocked
+                && offset.y <= MAX_JUMP_HEIGHT
+                && offset.x.abs() <= MAX_JUMP_DISTANCE)
+                .then_some(Link::Jump);
+        }
+        // The target has to be past the edge, or the enemy would keep standing on the surface.
+        let edge = from.edge?;
+        let past_left = from.rect.min.x - to.position.x;
+        let past_right = to.position.x - from.rect.max.x;
+        let past = if edge < 0.0 {
+            past_left
+        } else if edge > 0.0 {
+            past_right
+        } else {
+            past_left.max(past_right)
+        };
+        (DROP_CLEARANCE..=MAX_DROP_DISTANCE)
+            .contains(&past)
+            .then_some(Link::Drop)
+    }
+    /// Node below or at the position, which an entity there stands on.
+    fn nearest(&self, position: Vec2) -> Option<usize> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.position.y <= position.y + ARRIVE_DISTANCE)
+            .min_by(|(_, x), (_, y)| {
+                x.position
+                    .distance(position)
+                    .total_cmp(&y.position.distance(position))
+            })
+            .map(|(i, _)| i)
+    }
+    /// Shortest path between the nodes nearest to both positions, found by A*. The node of
+    /// the start is not part of the path.
+    pub fn path(&self, from: Vec2, to: Vec2) -> Option<VecDeque<Waypoint>> {
+        let start = self.nearest(from)?;
+        let goal = self.nearest(to)?;
+        let goal_position = self.nodes[goal].position;
+
+        let mut costs = vec![f32::INFINITY; self.nodes.len()];
+        let mut previous: Vec<Option<(usize, Link)>> = vec![None; self.nodes.len()];
+        let mut open = BinaryHeap::new();
+        costs[start] = 0.0;
+        open.push(Candidate {
+            estimate: self.nodes[start].position.distance(goal_position),
+            node: start,
+        });
+        while let Some(Candidate { node, .. }) = open.pop() {
+            if node == goal {
+                break;
+            }
+            for &(next, link) in &self.links[node] {
+                let distance = self.nodes[node]
+                    .position
+                    .distance(self.nodes[next].position);
+                let cost = costs[node]
+                    + match link {
+                        Link::Jump => distance + JUMP_COST,
+                        Link::Walk | Link::Drop => distance,
+                    };
+                if cost < costs[next] {
+                    costs[next] = cost;
+                    previous[next] = Some((node, link));
+                    open.push(Candidate {
+                        estimate: cost + self.nodes[next].position.distance(goal_position),
+                        node: next,
+                    });
+                }
+            }
+        }
+
+        let mut waypoints = VecDeque::new();
+        let mut node = goal;
+        while node != start {
+            let (from, link) = previous[node]?;
+            waypoints.push_front(Waypoint {
+                position: self.nodes[node].position,
+                link,
+            });
+            node = from;
+        }
+        Some(waypoints)
+    }
+}
+
+/// Node in the open set of A*, ordered so that the lowest estimate is popped first.
+struct Candidate {
+    estimate: f32,
+    node: usize,
+}
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+impl Eq for Candidate {}
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate)
+    }
+}
+
+/// Next node of a path, together with the link leading to it.
+#[derive(Clone, Copy, Debug)]
+pub struct Waypoint {
+    pub position: Vec2,
+    pub link: Link,
+}
+impl Waypoint {
+    fn reached(&self, position: Vec2) -> bool {
+        let above = position.y - self.position.y;
+        (position.x - self.position.x).abs() < ARRIVE_DISTANCE
+            && (0.0..STANDING_HEIGHT).contains(&above)
+    }
+}
+
+/// Path of a ground enemy over the `NavGraph`.
+#[derive(Component)]
+pub struct NavPath {
+    waypoints: VecDeque<Waypoint>,
+    repath: Timer,
+}
+impl Default for NavPath {
+    fn default() -> Self {
+        NavPath {
+            waypoints: VecDeque::new(),
+            repath: Timer::from_seconds(REPATH_INTERVAL, TimerMode::Repeating),
+        }
+    }
+}
+impl NavPath {
+    /// Horizontal direction toward the next waypoint, if there is any.
+    pub fn direction(&self, position: Vec2) -> Option<Vec2> {
+        self.waypoints
+            .front()
+            .map(|waypoint| Vec2::new((waypoint.position.x - position.x).signum(), 0.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FLOOR: f32 = 0.5;
+    const PLATFORM: f32 = 1.8;
+
+    /// Floor and the lower platform of the first stage.
+    fn terrain() -> [Rect; 2] {
+        [
+            Rect::new(-50.0, -0.5, 50.0, FLOOR),
+            Rect::new(-6.0, 1.3, -2.0, PLATFORM),
+        ]
+    }
+    /// Positions of the ends of all links of the given kind.
+    fn links(graph: &NavGraph, kind: Link) -> Vec<(Vec2, Vec2)> {
+        graph
+            .links
+            .iter()
+            .enumerate()
+            .flat_map(|(from, links)| {
+                links
+                    .iter()
+                    .filter(move |(_, link)| *link == kind)
+                    .map(move |(to, _)| (graph.nodes[from].position, graph.nodes[*to].position))
+            })
+            .collect()
+    }
+    /// First waypoint of the path reached by dropping.
+    fn first_drop(graph: &NavGraph, from: Vec2, to: Vec2) -> Waypoint {
+        *graph
+            .path(from, to)
+            .unwrap()
+            .iter()
+            .find(|waypoint| waypoint.link == Link::Drop)
+            .unwrap()
+    }
+
+    #[test]
+    fn walk_links_connect_neighbours_of_a_surface() {
+        let graph = NavGraph::from_terrain(&terrain()[..1]);
+        let walks = links(&graph, Link::Walk);
+        assert!(walks.iter().all(|(from, to)| from.y == to.y));
+        // Every node but the last one walks to its right neighbour.
+        let right = walks.iter().filter(|(from, to)| to.x > from.x).count();
+        assert_eq!(right, graph.nodes.len() - 1);
+        assert!(links(&graph, Link::Jump).is_empty());
+        assert!(links(&graph, Link::Drop).is_empty());
+    }
+    #[test]
+    fn jumps_lead_up_onto_the_platform_without_bumping_the_head() {
+        let graph = NavGraph::from_terrain(&terrain());
+        let jumps = links(&graph, Link::Jump);
+        assert!(
+            jumps
+                .iter()
+                .any(|(from, to)| from.y == FLOOR && to.y == PLATFORM)
+        );
+        // Right under the platform, a jump would hit its bottom.
+        assert!(
+            jumps
+                .iter()
+                .all(|(from, _)| from.y != FLOOR || !(-6.0..-2.0).contains(&from.x))
+        );
+    }
+    #[test]
+    fn drops_lead_past_the_edges_of_the_platform() {
+        let graph = NavGraph::from_terrain(&terrain());
+        let drops = links(&graph, Link::Drop);
+        assert!(drops.iter().any(|(_, to)| to.x < -6.0));
+        assert!(drops.iter().any(|(_, to)| to.x > -2.0));
+        // Nodes of the floor right below the edges would leave the enemy on the platform.
+        assert!(drops.iter().all(|(from, to)| {
+            from.y == PLATFORM
+                && to.y == FLOOR
+                && (to.x <= -6.0 - DROP_CLEARANCE || to.x >= -2.0 + DROP_CLEARANCE)
+        }));
+    }
+    #[test]
+    fn path_jumps_up_onto_the_platform() {
+        let graph = NavGraph::from_terrain(&terrain());
+        let path = graph
+            .path(Vec2::new(5.0, 1.0), Vec2::new(-4.0, 2.3))
+            .unwrap();
+        assert!(path.iter().any(|waypoint| waypoint.link == Link::Jump));
+        assert!(path.iter().all(|waypoint| waypoint.link != Link::Drop));
+        assert_eq!(path.back().unwrap().position.y, PLATFORM);
+    }
+    #[test]
+    fn path_drops_off_the_platform_past_its_edge() {
+        // Even toward the floor right below, the enemy has to drop over an edge.
+        let graph = NavGraph::from_terrain(&terrain());
+        let left = first_drop(&graph, Vec2::new(-4.0, 2.3), Vec2::new(-5.0, 1.0));
+        assert!(left.position.x <= -6.0 - DROP_CLEARANCE);
+        let right = first_drop(&graph, Vec2::new(-4.0, 2.3), Vec2::new(-3.0, 1.0));
+        assert!(right.position.x >= -2.0 + DROP_CLEARANCE);
+    }
+}
@@ -0,0 +1,159 @@
+use super::CollisionGroup;
+use super::Player;
+use super::damage::Hitstun;
+use super::status::StatusEffects;
+
+use avian2d::prelude::*;
+use bevy::prelude::*;
+use serde::Deserialize;
+
+const JUMP_KEY: KeyCode = KeyCode::Space;
+/// How far below the feet the ground is still considered to be touched.
+const GROUND_CHECK_DISTANCE: f32 = 0.05;
+
+/// Shape cast from the feet of an entity of given collider size, that finds the ground below.
+pub fn ground_caster(collider_width: f32, collider_height: f32) -> ShapeCaster {
+    ShapeCaster::new(
+        Collider::rectangle(0.9 * collider_width, GROUND_CHECK_DISTANCE),
+        Vec2::new(0.0, -collider_height / 2.0),
+        0.0,
+        Dir2::NEG_Y,
+    )
+    .with_max_distance(GROUND_CHECK_DISTANCE)
+    .with_query_filter(SpatialQueryFilter::from_mask(CollisionGroup::Terrain))
+}
+pub fn update_grounded(
+    time_fixed: Res<Time>,
+    mut q_controllers: Query<(&mut JumpState, &ShapeHits)>,
+) {
+    let dt = time_fixed.delta_secs();
+    q_controllers.iter_mut().for_each(|(mut state, hits)| {
+        state.grounded = !hits.is_empty();
+        if state.grounded {
+            state.since_grounded = 0.0;
+        } else {
+            state.since_grounded += dt;
+        }
+    });
+}
+/// Jumps when the jump was pressed recently enough and the player stood on the ground
+/// recently enough. Releasing the jump early cuts the jump short.
+pub fn jump(
+    time_fixed: Res<Time>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut q_player: Query<
+        (
+            &PlatformerController,
+            &mut JumpState,
+            &mut LinearVelocity,
+            &StatusEffects,
+            Has<Hitstun>,
+        ),
+        With<Player>,
+    >,
+) -> Result {
+    let (controller, mut state, mut velocity, effects, hitstun) = q_player.single_mut()?;
+
+    if keyboard.just_pressed(JUMP_KEY) {
+        state.since_jump_pressed = 0.0;
+    } else {
+        state.since_jump_pressed += time_fixed.delta_secs();
+    }
+    // The buffered press may still jump once the hitstun or stun wears off.
+    if hitstun || effects.stunned() {
+        return Ok(());
+    }
+
+    if state.since_jump_pressed <= controller.jump_buffer
+        && state.since_grounded <= controller.coyote_time
+        && !state.rising
+    {
+        velocity.y = controller.jump_speed;
+        state.rising = true;
+        // Consume both, so that the same press or the same ground does not jump twice.
+        state.since_jump_pressed = f32::INFINITY;
+        state.since_grounded = f32::INFINITY;
+    }
+
+    if state.rising && (velocity.y <= 0.0 || !keyboard.pressed(JUMP_KEY)) {
+        if velocity.y > 0.0 {
+            velocity.y *= controller.jump_cut;
+        }
+        state.rising = false;
+    }
+
+    Ok(())
+}
+
+/// Tunable values of the platformer movement.
+#[derive(Component, Deserialize, Clone)]
+#[serde(default)]
+pub struct PlatformerController {
+    /// Upward velocity at the start of the jump.
+    pub jump_speed: f32,
+    /// Multiplier of the upward velocity when the jump is released before the peak.
+    pub jump_cut: f32,
+    /// Seconds after leaving the ground during which jumping is still possible.
+    pub coyote_time: f32,
+    /// Seconds before landing during which the jump press is remembered.
+    pub jump_buffer: f32,
+    /// Fraction of the horizontal acceleration available in the air.
+    pub air_control: f32,
+    /// Rate of speeding up towards the maximum speed.
+    pub acceleration: f32,
+    /// Rate of slowing down when moving faster than intended or turning around.
+    pub deceleration: f32,
+    /// Rate of slowing down when there is no movement input.
+    pub friction: f32,
+}
+impl PlatformerController {
+    /// Moves the horizontal `velocity` towards the `target` velocity.
+    pub fn accelerate(&self, velocity: f32, target: f32, grounded: bool, dt: f32) -> f32 {
+        let rate = if target == 0.0 {
+            self.friction
+        } else if velocity * target < 0.0 || velocity.abs() > target.abs() {
+            self.deceleration
+        } else {
+            self.acceleration
+        };
+        let rate = if grounded {
+            rate
+        } else {
+            self.air_control * rate
+        };
+        velocity + (target - velocity).clamp(-rate * dt, rate * dt)
+    }
+}
+impl Default for PlatformerController {
+    fn default() -> Self {
+        Self {
+            jump_speed: 7.0,
+            jump_cut: 0.4,
+            coyote_time: 0.1,
+            jump_buffer: 0.1,
+            air_control: 0.5,
+            acceleration: 30.0,
+            deceleration: 50.0,
+            friction: 20.0,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct JumpState {
+    pub grounded: bool,
+    since_grounded: f32,
+    since_jump_pressed: f32,
+    /// Going up because of a jump, which can still be cut short.
+    rising: bool,
+}
+impl Default for JumpState {
+    fn default() -> Self {
+        Self {
+            grounded: false,
+            since_grounded: f32::INFINITY,
+            since_jump_pressed: f32::INFINITY,
+            rising: false,
+        }
+    }
+}
@@ -2,77 +2,108 @@ use super::CollisionGroup;
 use super::Enemy;
 use super::Equipment;
 use super::Health;
-use super::PLAYER_MAX_HEALTH;
 use super::Player;
-use super::PlayerDamage;
 use super::PlayerDeath;
-use super::Projectile;
 use super::SPRITE_ORIENTATION;
 use super::Stats;
+use super::ballistics::ProjectileModifiers;
+use super::bullets::EnemyProjectile;
+use super::champion::SelectedDefinition;
+use super::damage::ContactDamage;
+use super::damage::Hit;
+use super::damage::Hitstun;
+use super::damage::Invulnerable;
+use super::damage::Shield;
+use super::platformer::JumpState;
+use super::platformer::PlatformerController;
+use super::platformer::ground_caster;
+use super::skills::Dashing;
+use super::skills::SkillSlots;
+use super::stats::BaseStats;
+use super::stats::Passives;
+use super::status::StatusEffects;
 use crate::Cursor;
-use crate::METERS_PER_PIXEL;
 use crate::MainState;
-use crate::utils::Lifetime;
 
 use avian2d::prelude::*;
 use bevy::prelude::*;
-use std::f32::consts::FRAC_1_SQRT_2;
-use std::mem::discriminant;
 
-const DASH_TIME: f32 = 0.4;
-const PROJECTILE_SIZE: f32 = METERS_PER_PIXEL * 2.0;
-const PROJECTILE_LIFETIME: f32 = 1.0;
-const PROJECTILE_SPEED: f32 = 50.0;
-const DIRECTION_RIGHT: Vec2 = Vec2::X;
-const DIRECTION_UPRIGHT: Vec2 = Vec2 {
-    x: FRAC_1_SQRT_2,
-    y: FRAC_1_SQRT_2,
-};
-const DIRECTION_UP: Vec2 = Vec2::Y;
-const DIRECTION_UPLEFT: Vec2 = Vec2 {
-    x: -FRAC_1_SQRT_2,
-    y: FRAC_1_SQRT_2,
-};
-const DIRECTION_LEFT: Vec2 = Vec2::NEG_X;
-const DIRECTION_DOWNLEFT: Vec2 = Vec2 {
-    x: -FRAC_1_SQRT_2,
-    y: -FRAC_1_SQRT_2,
-};
-const DIRECTION_DOWN: Vec2 = Vec2::NEG_Y;
-const DIRECTION_DOWNRIGHT: Vec2 = Vec2 {
-    x: FRAC_1_SQRT_2,
-    y: -FRAC_1_SQRT_2,
-};
+/// Dash velocity relative to the `movement_speed`.
+const DASH_SPEED_FACTOR: f32 = 3.0;
+/// Maximum speed while attacking, relative to the `movement_speed`.
+const ATTACKING_SPEED_FACTOR: f32 = 0.5;
+/// Impulse with which touching enemies push the player away, and are pushed away in turn.
+const CONTACT_KNOCKBACK: f32 = 4.0;
+const CONTACT_HITSTUN: f32 = 0.25;
+
+pub fn spawn(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    champion: SelectedDefinition,
+) -> Result {
+    let champion = champion.get()?;
+    let (collider_width, collider_height) = champion.collider;
 
-pub fn spawn(mut commands: Commands) {
     commands.spawn((
         Name::new("Player"),
         Player,
         Looking(SPRITE_ORIENTATION),
-        DashTimer::default(),
-        AttackTimer::default(),
-        Health(PLAYER_MAX_HEALTH),
-        Equipment::default(),
-        Stats::default(),
+        MovementInput::default(),
+        (
+            SkillSlots::new(
+                champion
+                    .skills
+                    .each_ref()
+                    .map(|definition| (definition.skill, definition.modifiers)),
+            ),
+            ProjectileModifiers::default(),
+        ),
+        (
+            Health(champion.stats.max_health),
+            Shield::new(champion.stats.shield),
+            champion.resistances.clone(),
+            champion.iframes.clone(),
+            StatusEffects::default(),
+        ),
+        (
+            Equipment::default(),
+            BaseStats(champion.stats.clone()),
+            champion.stats.clone(),
+            Passives(champion.passives.clone()),
+        ),
         PlayerState::default(),
         Transform::from_translation(Vec3::new(0.0, 1.0, 1.0)),
-        Sprite::from_color(Color::WHITE, Vec2::new(1.0, 2.0)),
+        champion.sprite.sprite(&asset_server),
+        (
+            champion.controller.clone(),
+            JumpState::default(),
+            ground_caster(collider_width, collider_height),
+        ),
         (
             RigidBody::Dynamic,
             LockedAxes::new().lock_rotation(),
-            Collider::rectangle(1.0, 2.0),
+            Collider::rectangle(collider_width, collider_height),
             CollidingEntities::default(),
             CollisionLayers::new(
                 CollisionGroup::Player,
-                [CollisionGroup::Enemy, CollisionGroup::Terrain],
+                [
+                    CollisionGroup::Enemy,
+                    CollisionGroup::Terrain,
+                    CollisionGroup::Hazard,
+                    CollisionGroup::EnemyProjectile,
+                ],
             ),
         ),
         DespawnOnExit(MainState::Game),
     ));
+
+    Ok(())
 }
-pub fn update_stats(mut q_player: Query<(&mut Stats, &Equipment)>) -> Result {
-    let (mut stats, eq) = q_player.single_mut()?;
-    stats.apply_equipment(&eq);
+pub fn update_projectile_modifiers(
+    mut q_player: Query<(&mut ProjectileModifiers, &Equipment)>,
+) -> Result {
+    let (mut modifiers, eq) = q_player.single_mut()?;
+    *modifiers = eq.projectile_modifiers();
     Ok(())
 }
 /// In case of high frame rate (bigger than `FixedTime` 64Hz), if one swift button press is registered and
@@ -81,56 +112,79 @@ pub fn update_stats(mut q_player: Query<(&mut Stats, &Equipment)>) -> Result {
 /// then the swift input is effectively discarded.
 pub fn handle_input(
     time_fixed: Res<Time>,
-    mouse: Res<ButtonInput<MouseButton>>,
     keyboard: Res<ButtonInput<KeyCode>>,
-    mut q_player: Query<(&mut LinearVelocity, &mut PlayerState, &mut DashTimer)>,
+    q_gamepads: Query<&Gamepad>,
+    mut q_player: Query<(
+        &mut LinearVelocity,
+        &mut PlayerState,
+        &mut MovementInput,
+        &SkillSlots,
+        &Stats,
+        &PlatformerController,
+        &JumpState,
+        &StatusEffects,
+        Option<&Dashing>,
+        Has<Hitstun>,
+    )>,
 ) -> Result {
-    let (mut velocity, mut state, mut dash_timer) = q_player.single_mut()?;
-
-    let left = keyboard.pressed(KeyCode::KeyA);
-    let right = keyboard.pressed(KeyCode::KeyD);
-    let down = keyboard.pressed(KeyCode::KeyS);
-    let up = keyboard.pressed(KeyCode::KeyW);
+    let (
+        mut velocity,
+        mut state,
+        mut movement,
+        slots,
+        stats,
+        controller,
+        jump_state,
+        effects,
+        dashing,
+        stunned,
+    ) = q_player.single_mut()?;
 
-    let direction = match (left, right, down, up) {
-        (false, false, false, false)
-        | (true, true, true, true)
-        | (true, true, false, false)
-        | (false, false, true, true) => Vec2::ZERO,
-        (false, true, false, false) | (false, true, true, true) => DIRECTION_RIGHT,
-        (false, true, false, true) => DIRECTION_UPRIGHT,
-        (false, false, false, true) | (true, true, false, true) => DIRECTION_UP,
-        (true, false, false, true) => DIRECTION_UPLEFT,
-        (true, false, false, false) | (true, false, true, true) => DIRECTION_LEFT,
-        (true, false, true, false) => DIRECTION_DOWNLEFT,
-        (false, false, true, false) | (true, true, true, false) => DIRECTION_DOWN,
-        (false, true, true, false) => DIRECTION_DOWNRIGHT,
+    let axis = |negative: KeyCode, positive: KeyCode| {
+        (keyboard.pressed(positive) as i8 - keyboard.pressed(negative) as i8) as f32
     };
-    let dash = direction != Vec2::ZERO
-        && keyboard.any_just_pressed(vec![KeyCode::ShiftLeft, KeyCode::Space]);
-    let attack = mouse.pressed(MouseButton::Left);
+    let keyboard_direction = Vec2::new(
+        axis(KeyCode::KeyA, KeyCode::KeyD),
+        axis(KeyCode::KeyS, KeyCode::KeyW),
+    )
+    .normalize_or_zero();
+    // Any tilted stick takes precedence over the keyboard.
+    let stick = q_gamepads
+        .iter()
+        .map(|gamepad| gamepad.left_stick())
+        .find(|stick| *stick != Vec2::ZERO);
+    movement.0 = stick.unwrap_or(keyboard_direction).clamp_length_max(1.0);
 
-    let dt = time_fixed.delta();
+    state.set_if_neq(match (dashing, slots.attack_held()) {
+        (Some(dashing), _) => PlayerState::Dashing(dashing.direction),
+        (None, true) => PlayerState::Attacking,
+        (None, false) => PlayerState::Idle,
+    });
 
-    if state.is_dashing() && dash_timer.0.tick(dt).is_finished() {
-        *state = PlayerState::Idle;
-        dash_timer.0.reset();
-    }
-    if !state.is_dashing() {
-        *state = match (dash, attack) {
-            (true, _) => PlayerState::Dashing(direction),
-            (false, true) => PlayerState::Attacking,
-            (false, false) => PlayerState::Idle,
-        };
+    // Knockback keeps its velocity until the hitstun wears off.
+    if stunned {
+        return Ok(());
     }
 
-    velocity.0 += 10.0
-        * dt.as_secs_f32()
+    // Vertical movement is left to jumping and gravity, except for the dash.
+    let max_speed = stats.movement_speed
+        * effects.movement_factor()
         * match *state {
-            PlayerState::Idle => 1.0 * direction,
-            PlayerState::Attacking => 0.3 * direction,
-            PlayerState::Dashing(direction) => 5.0 * direction,
+            PlayerState::Idle => 1.0,
+            PlayerState::Attacking => ATTACKING_SPEED_FACTOR,
+            PlayerState::Dashing(_) => DASH_SPEED_FACTOR,
         };
+    match *state {
+        PlayerState::Dashing(direction) => velocity.0 = max_speed * direction,
+        PlayerState::Idle | PlayerState::Attacking => {
+            velocity.x = controller.accelerate(
+                velocity.x,
+                max_speed * movement.0.x,
+                jump_state.grounded,
+                time_fixed.delta_secs(),
+            )
+        }
+    }
     Ok(())
 }
 pub fn visual_state(mut query: Query<(&mut Sprite, &PlayerState), Changed<PlayerState>>) {
@@ -142,36 +196,54 @@ pub fn visual_state(mut query: Query<(&mut Sprite, &PlayerState), Changed<Player
             PlayerState::Dashing(_) => sprite.color = Color::srgb(0.1, 0.1, 1.0),
         })
 }
+/// Hurts the player touching enemies or their projectiles, and pushes both apart, so that enemies
+/// do not pile up on the player.
 pub fn hit(
-    q_player: Query<(&CollidingEntities, &PlayerState), With<Player>>,
-    q_enemies: Query<Entity, With<Enemy>>,
-    mut damage_messages: MessageWriter<PlayerDamage>,
+    mut commands: Commands,
+    q_player: Query<
+        (
+            Entity,
+            &GlobalTransform,
+            &CollidingEntities,
+            Has<Invulnerable>,
+        ),
+        With<Player>,
+    >,
+    q_enemies: Query<(&GlobalTransform, &ContactDamage), Or<(With<Enemy>, With<EnemyProjectile>)>>,
+    mut q_pushed: Query<Forces, (With<Enemy>, Without<Player>, Without<Hitstun>)>,
+    mut hit_messages: MessageWriter<Hit>,
 ) -> Result {
-    let (colliding_entities, player_state) = q_player.single()?;
-    let damage = match *player_state {
-        PlayerState::Dashing(_) => 0.0,
-        _ => colliding_entities
-            .iter()
-            .filter(|e| q_enemies.contains(**e))
-            .count() as f32,
-    };
+    let (player, player_transform, colliding_entities, invulnerable) = q_player.single()?;
 
-    if damage >= 0.0 {
-        damage_messages.write(PlayerDamage(damage));
-    }
+    // Hits during the invulnerability after the first one are ignored by `apply_hits`.
+    colliding_entities.iter().for_each(|enemy| {
+        let Ok((enemy_transform, contact_damage)) = q_enemies.get(*enemy) else {
+            return;
+        };
+        let away = (player_transform.translation() - enemy_transform.translation())
+            .xy()
+            .normalize_or_zero();
+        hit_messages.write(
+            Hit::new(*enemy, player, contact_damage.0)
+                .with_knockback(CONTACT_KNOCKBACK * away, CONTACT_HITSTUN),
+        );
+        // Only contacts that hurt push, so that dashing through enemies does not stun them. The
+        // hitstun keeps the enemy from walking right back, and from being pushed every tick.
+        if !invulnerable && let Ok(mut forces) = q_pushed.get_mut(*enemy) {
+            forces.apply_linear_impulse(-CONTACT_KNOCKBACK * away);
+            commands
+                .entity(*enemy)
+                .try_insert(Hitstun::new(CONTACT_HITSTUN));
+        }
+    });
 
     Ok(())
 }
-pub fn take_damage(
-    mut q_player: Query<&mut Health, With<Player>>,
-    mut damage_messages: MessageReader<PlayerDamage>,
+pub fn check_death(
+    q_player: Query<&Health, With<Player>>,
     mut death_messages: MessageWriter<PlayerDeath>,
 ) -> Result {
-    let mut health = q_player.single_mut()?;
-
-    health.0 -= damage_messages.read().map(|message| message.0).sum::<f32>();
-
-    if health.0 <= 0.0 {
+    if q_player.single()?.0 <= 0.0 {
         death_messages.write_default();
     }
 
@@ -191,63 +263,7 @@ pub fn update_looking(
 
     Ok(())
 }
-pub fn attack(
-    time_fixed: Res<Time>,
-    mut commands: Commands,
-    mut q_player: Query<
-        (
-            &mut AttackTimer,
-            &GlobalTransform,
-            &Looking,
-            &PlayerState,
-            &Stats,
-        ),
-        With<Player>,
-    >,
-) -> Result {
-    let (mut attack_timer, player_transform, player_looking, player_state, stats) =
-        q_player.single_mut()?;
-    let player_position = player_transform.translation();
-
-    attack_timer.tick(time_fixed.delta().mul_f32(stats.attack_speed));
-
-    if *player_state == PlayerState::Attacking && attack_timer.0.is_finished() {
-        commands.spawn((
-            Name::new("Projectile"),
-            Projectile,
-            Sprite::from_color(Color::WHITE, Vec2::from((PROJECTILE_SIZE, PROJECTILE_SIZE))),
-            Transform::from_translation(player_position),
-            RigidBody::Kinematic,
-            Collider::rectangle(PROJECTILE_SIZE, PROJECTILE_SIZE),
-            LinearVelocity(PROJECTILE_SPEED * player_looking.0),
-            DespawnOnExit(MainState::Game),
-            Lifetime::new(PROJECTILE_LIFETIME),
-            CollisionLayers::new(
-                CollisionGroup::Projectile,
-                [CollisionGroup::Terrain, CollisionGroup::Enemy],
-            ),
-        ));
-        attack_timer.0.reset();
-    }
-
-    Ok(())
-}
 
-#[derive(Component)]
-pub struct DashTimer(Timer);
-impl Default for DashTimer {
-    fn default() -> Self {
-        DashTimer(Timer::from_seconds(DASH_TIME, TimerMode::Once))
-    }
-}
-/// Timer of 1 second, scaled by the `Stats` `attack_speed`
-#[derive(Component, Deref, DerefMut)]
-pub struct AttackTimer(Timer);
-impl Default for AttackTimer {
-    fn default() -> Self {
-        AttackTimer(Timer::from_seconds(1.0, TimerMode::Once))
-    }
-}
 #[derive(Component, PartialEq, Default)]
 pub enum PlayerState {
     #[default]
@@ -255,11 +271,9 @@ pub enum PlayerState {
     Dashing(Vec2),
     Attacking,
 }
-impl PlayerState {
-    fn is_dashing(&self) -> bool {
-        discriminant(self) == discriminant(&PlayerState::Dashing(Vec2::ZERO))
-    }
-}
 /// Normalized vector of direction the entity is looking.
 #[derive(Component)]
-pub struct Looking(Vec2);
+pub struct Looking(pub Vec2);
+/// Direction the player wants to move in, as read from the input, with length up to 1.
+#[derive(Component, Default)]
+pub struct MovementInput(pub Vec2);
@@ -0,0 +1,558 @@
+use super::CollisionGroup;
+use super::Enemy;
+use super::Projectile;
+use super::RunRng;
+use super::Stats;
+use super::ballistics::ProjectileModifiers;
+use super::damage::CRIT_COLOR;
+use super::damage::Damage;
+use super::damage::DamageFalloff;
+use super::damage::DamageType;
+use super::damage::Invulnerable;
+use super::explosion::Explosion;
+use super::explosion::Fuse;
+use super::player::Looking;
+use super::player::MovementInput;
+use super::status::Status;
+use super::status::StatusEffects;
+use super::status::StatusKind;
+use super::summons::MAX_ROBOTS;
+use super::summons::Summon;
+use crate::Cursor;
+use crate::METERS_PER_PIXEL;
+use crate::MainState;
+use crate::utils::Lifetime;
+
+use avian2d::prelude::*;
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::cmp::Ordering;
+
+const SLOT_BINDINGS: [Binding; 4] = [
+    Binding::Mouse(MouseButton::Left),
+    Binding::Mouse(MouseButton::Right),
+    Binding::Key(KeyCode::ShiftLeft),
+    Binding::Key(KeyCode::KeyQ),
+];
+const DASH_TIME: f32 = 0.4;
+const PROJECTILE_SIZE: f32 = METERS_PER_PIXEL * 2.0;
+const PROJECTILE_LIFETIME: f32 = 1.0;
+const PROJECTILE_SPEED: f32 = 50.0;
+const CRIT_PROJECTILE_SCALE: f32 = 1.5;
+const RIFLE_DAMAGE: f32 = 1.0;
+const SHOTGUN_PELLETS: u32 = 7;
+/// Angle of the cone in radians.
+const SHOTGUN_SPREAD: f32 = 0.6;
+/// Velocity change of the caster, opposite to where it is looking.
+const SHOTGUN_RECOIL: f32 = 4.0;
+const PELLET_SPEED: f32 = 40.0;
+const PELLET_LIFETIME: f32 = 0.25;
+const PELLET_DAMAGE: f32 = 1.0;
+const GRENADE_SIZE: f32 = METERS_PER_PIXEL * 4.0;
+const GRENADE_SPEED: f32 = 12.0;
+const GRENADE_RESTITUTION: f32 = 0.5;
+const GRENADE_FUSE: f32 = 1.5;
+const GRENADE_RADIUS: f32 = 3.0;
+const GRENADE_DAMAGE: f32 = 5.0;
+const GRENADE_IMPULSE: f32 = 2.0;
+const GRENADE_STUN: f32 = 0.75;
+/// Maximum distance between the cursor and the enemy that gets marked.
+const TARGET_LOCK_RADIUS: f32 = 3.0;
+const MARK_DURATION: f32 = 5.0;
+/// Additional fraction of damage that marked enemies take from summons.
+const MARK_AMPLIFICATION: f32 = 0.5;
+const MARK_SIZE: Vec2 = Vec2::splat(0.2);
+const MARK_OFFSET: Vec3 = Vec3::new(0.0, 0.5, 0.1);
+
+/// Ticks cooldowns of all slots and sends `SkillUsed` for every slot that got triggered,
+/// unless the caster is stunned.
+pub fn trigger(
+    time_fixed: Res<Time>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut q_casters: Query<(
+        Entity,
+        &mut SkillSlots,
+        &Stats,
+        &MovementInput,
+        Option<&StatusEffects>,
+    )>,
+    q_summons: Query<&Summon>,
+    mut skill_messages: MessageWriter<SkillUsed>,
+) {
+    let dt = time_fixed.delta();
+    q_casters
+        .iter_mut()
+        .for_each(|(caster, mut slots, stats, movement, effects)| {
+            let stunned = effects.is_some_and(|effects| effects.stunned());
+            let context = SkillContext {
+                direction: movement.0,
+                summons: q_summons
+                    .iter()
+                    .filter(|summon| summon.owner == caster)
+                    .count(),
+            };
+            slots.0.iter_mut().for_each(|slot| {
+                slot.cooldown
+                    .tick(dt.mul_f32(slot.skill.cooldown_rate(stats)));
+                slot.held = slot.binding.pressed(&mouse, &keyboard);
+                let triggered = match slot.skill.activation() {
+                    Activation::Held => slot.held,
+                    Activation::Pressed => slot.binding.just_pressed(&mouse, &keyboard),
+                };
+                if triggered
+                    && !stunned
+                    && slot.cooldown.is_finished()
+                    && slot.skill.condition(&context)
+                {
+                    slot.cooldown.reset();
+                    skill_messages.write(SkillUsed {
+                        caster,
+                        skill: slot.skill,
+                        modifiers: slot.modifiers,
+                    });
+                }
+            });
+        });
+}
+pub fn rifle(
+    mut commands: Commands,
+    mut rngs: ResMut<RunRng>,
+    mut skill_messages: MessageReader<SkillUsed>,
+    q_casters: Query<(
+        &GlobalTransform,
+        &Looking,
+        &Stats,
+        Option<&ProjectileModifiers>,
+    )>,
+) {
+    skill_messages
+        .read()
+        .filter(|message| message.skill == Skill::Rifle)
+        .for_each(|message| {
+            let Ok((transform, looking, stats, equipment_modifiers)) =
+                q_casters.get(message.caster)
+            else {
+                return;
+            };
+            commands.spawn((
+                projectile(
+                    Projectile::new(
+                        message.caster,
+                        stats.roll_damage(RIFLE_DAMAGE, DamageType::Piercing, &mut rngs.damage),
+                    ),
+                    transform.translation(),
+                    PROJECTILE_SPEED * looking.0,
+                    PROJECTILE_LIFETIME,
+                ),
+                message
+                    .modifiers
+                    .combine(equipment_modifiers.copied().unwrap_or_default()),
+            ));
+        });
+}
+/// Fires `SHOTGUN_PELLETS` evenly spread over the `SHOTGUN_SPREAD` cone and pushes the caster back.
+pub fn shotgun(
+    mut commands: Commands,
+    mut rngs: ResMut<RunRng>,
+    mut skill_messages: MessageReader<SkillUsed>,
+    mut q_casters: Query<(
+        &GlobalTransform,
+        &Looking,
+        &Stats,
+        Option<&ProjectileModifiers>,
+        &mut LinearVelocity,
+    )>,
+) {
+    skill_messages
+        .read()
+        .filter(|message| message.skill == Skill::Shotgun)
+        .for_each(|message| {
+            let Ok((transform, looking, stats, equipment_modifiers, mut velocity)) =
+                q_casters.get_mut(message.caster)
+            else {
+                return;
+            };
+            let modifiers = message
+                .modifiers
+                .combine(equipment_modifiers.copied().unwrap_or_default());
+            let position = transform.translation();
+            (0..SHOTGUN_PELLETS).for_each(|i| {
+                let angle = SHOTGUN_SPREAD * (i as f32 / (SHOTGUN_PELLETS - 1) as f32 - 0.5);
+                commands.spawn((
+                    projectile(
+                        Projectile::new(
+                            message.caster,
+                            stats.roll_damage(
+                                PELLET_DAMAGE,
+                                DamageType::Piercing,
+                                &mut rngs.damage,
+                            ),
+                        ),
+                        position,
+                        PELLET_SPEED * Vec2::from_angle(angle).rotate(looking.0),
+                        PELLET_LIFETIME,
+                    ),
+                    DamageFalloff {
+                        origin: position.xy(),
+                        range: PELLET_SPEED * PELLET_LIFETIME,
+                    },
+                    modifiers,
+                ));
+            });
+            velocity.0 -= SHOTGUN_RECOIL * looking.0;
+        });
+}
+/// Throws a bouncing grenade, which explodes after a while or on contact with an enemy.
+pub fn grenade(
+    mut commands: Commands,
+    mut rngs: ResMut<RunRng>,
+    mut skill_messages: MessageReader<SkillUsed>,
+    q_casters: Query<(Entity, &GlobalTransform, &Looking, &Stats)>,
+) {
+    skill_messages
+        .read()
+        .filter(|message| message.skill == Skill::Grenade)
+        .filter_map(|message| q_casters.get(message.caster).ok())
+        .for_each(|(caster, transform, looking, stats)| {
+            commands.spawn((
+                Name::new("Grenade"),
+                Fuse {
+                    timer: Timer::from_seconds(GRENADE_FUSE, TimerMode::Once),
+                    explosion: Explosion {
+                        source: caster,
+                        position: Vec2::ZERO,
+                        radius: GRENADE_RADIUS,
+                        damage: stats.roll_damage(
+                            GRENADE_DAMAGE,
+                            DamageType::Explosive,
+                            &mut rngs.damage,
+                        ),
+                        impulse: GRENADE_IMPULSE,
+                        status: Some(Status {
+                            kind: StatusKind::Stun,
+                            duration: GRENADE_STUN,
+                            potency: 0.0,
+                        }),
+                        hostile: false,
+                    },
+                },
+                Sprite::from_color(Color::srgb(0.2, 0.4, 0.1), Vec2::splat(GRENADE_SIZE)),
+                Transform::from_translation(transform.translation()),
+                RigidBody::Dynamic,
+                Collider::circle(GRENADE_SIZE / 2.0),
+                Restitution::new(GRENADE_RESTITUTION),
+                LinearVelocity(GRENADE_SPEED * looking.0),
+                CollidingEntities::default(),
+                CollisionLayers::new(
+                    CollisionGroup::Projectile,
+                    [CollisionGroup::Terrain, CollisionGroup::Enemy],
+                ),
+                DespawnOnExit(MainState::Game),
+            ));
+        });
+}
+/// Marks the enemy nearest to the cursor, making it take more damage from summons.
+pub fn target_lock(
+    mut commands: Commands,
+    mut skill_messages: MessageReader<SkillUsed>,
+    q_cursor: Query<&Cursor>,
+    mut q_enemies: Query<(Entity, &GlobalTransform, Option<&mut Marked>), With<Enemy>>,
+) -> Result {
+    let cursor = q_cursor.single()?;
+    skill_messages
+        .read()
+        .filter(|message| message.skill == Skill::TargetLock)
+        .for_each(|_| {
+            let Some(cursor_position) = cursor.0 else {
+                return;
+            };
+            let nearest = q_enemies
+                .iter_mut()
+                .map(|(entity, transform, marked)| {
+                    let distance = cursor_position.distance(transform.translation().xy());
+                    (entity, marked, distance)
+                })
+                .filter(|(_, _, distance)| *distance < TARGET_LOCK_RADIUS)
+                .min_by(|(_, _, x), (_, _, y)| x.partial_cmp(y).unwrap_or(Ordering::Equal));
+            match nearest {
+                // Marking already marked enemy only refreshes the duration.
+                Some((_, Some(mut marked), _)) => marked.timer.reset(),
+                Some((entity, None, _)) => {
+                    let indicator = commands
+                        .spawn((
+                            Name::new("Mark"),
+                            Sprite::from_color(Color::srgb(1.0, 0.1, 0.1), MARK_SIZE),
+                            Transform::from_translation(MARK_OFFSET),
+                        ))
+                        .id();
+                    commands
+                        .entity(entity)
+                        .insert(Marked {
+                            timer: Timer::from_seconds(MARK_DURATION, TimerMode::Once),
+                            indicator,
+                        })
+                        .add_child(indicator);
+                }
+                None => (),
+            }
+        });
+
+    Ok(())
+}
+pub fn expire_marks(
+    time_fixed: Res<Time>,
+    mut commands: Commands,
+    mut q_marked: Query<(Entity, &mut Marked)>,
+) {
+    let dt = time_fixed.delta();
+    q_marked.iter_mut().for_each(|(entity, mut marked)| {
+        if marked.timer.tick(dt).is_finished() {
+            commands.entity(marked.indicator).despawn();
+            commands.entity(entity).remove::<Marked>();
+        }
+    });
+}
+pub fn dash(
+    mut commands: Commands,
+    mut skill_messages: MessageReader<SkillUsed>,
+    mut q_casters: Query<(&MovementInput, Option<&mut Invulnerable>)>,
+) {
+    skill_messages
+        .read()
+        .filter(|message| message.skill == Skill::Dash)
+        .for_each(|message| {
+            let Ok((movement, invulnerable)) = q_casters.get_mut(message.caster) else {
+                return;
+            };
+            let mut caster = commands.entity(message.caster);
+            caster.insert(Dashing {
+                direction: movement.0.normalize_or_zero(),
+                timer: Timer::from_seconds(DASH_TIME, TimerMode::Once),
+            });
+            match invulnerable {
+                Some(mut invulnerable) => invulnerable.extend(DASH_TIME),
+                None => {
+                    caster.insert(Invulnerable::new(DASH_TIME));
+                }
+            }
+        });
+}
+pub fn end_dash(
+    time_fixed: Res<Time>,
+    mut commands: Commands,
+    mut q_dashing: Query<(Entity, &mut Dashing)>,
+) {
+    let dt = time_fixed.delta();
+    q_dashing.iter_mut().for_each(|(entity, mut dashing)| {
+        if dashing.timer.tick(dt).is_finished() {
+            commands.entity(entity).remove::<Dashing>();
+        }
+    });
+}
+
+/// Projectile which damages the first enemy it touches, unless its `ProjectileModifiers` say otherwise.
+/// Critical projectiles are bigger and golden.
+pub fn projectile(data: Projectile, position: Vec3, velocity: Vec2, lifetime: f32) -> impl Bundle {
+    let sprite = if data.damage.critical {
+        Sprite::from_color(
+            CRIT_COLOR,
+            Vec2::splat(CRIT_PROJECTILE_SCALE * PROJECTILE_SIZE),
+        )
+    } else {
+        Sprite::from_color(Color::WHITE, Vec2::splat(PROJECTILE_SIZE))
+    };
+    (
+        Name::new("Projectile"),
+        data,
+        sprite,
+        Transform::from_translation(position),
+        RigidBody::Kinematic,
+        Collider::rectangle(PROJECTILE_SIZE, PROJECTILE_SIZE),
+        LinearVelocity(velocity),
+        DespawnOnExit(MainState::Game),
+        Lifetime::new(lifetime),
+        CollisionLayers::new(
+            CollisionGroup::Projectile,
+            [CollisionGroup::Terrain, CollisionGroup::Enemy],
+        ),
+    )
+}
+
+/// Every skill that can be put into one of the champion's slots.
+/// Adding a skill means adding a variant here and a system that reacts to its `SkillUsed`.
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Skill {
+    Rifle,
+    Shotgun,
+    Dash,
+    Grenade,
+    TargetLock,
+    RobotSummon,
+}
+impl Skill {
+    /// Base cooldown in seconds.
+    fn cooldown(&self) -> f32 {
+        match self {
+            Self::Rifle => 1.0,
+            Self::Shotgun => 1.5,
+            Self::Dash => DASH_TIME,
+            Self::Grenade => 4.0,
+            Self::TargetLock => 6.0,
+            Self::RobotSummon => 8.0,
+        }
+    }
+    pub fn category(&self) -> SkillCategory {
+        match self {
+            Self::Rifle | Self::Shotgun => SkillCategory::Attack,
+            Self::Dash | Self::Grenade | Self::TargetLock | Self::RobotSummon => {
+                SkillCategory::Ability
+            }
+        }
+    }
+    /// How fast the cooldown passes, relative to time.
+    fn cooldown_rate(&self, stats: &Stats) -> f32 {
+        match self.category() {
+            SkillCategory::Attack => stats.attack_speed,
+            SkillCategory::Ability => 1.0 + stats.ability_haste,
+        }
+    }
+    fn activation(&self) -> Activation {
+        match self {
+            Self::Rifle => Activation::Held,
+            _ => Activation::Pressed,
+        }
+    }
+    fn condition(&self, context: &SkillContext) -> bool {
+        match self {
+            Self::Dash => context.direction != Vec2::ZERO,
+            Self::RobotSummon => context.summons < MAX_ROBOTS,
+            _ => true,
+        }
+    }
+}
+
+/// Decides which `Stats` scale the cooldown of a skill.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SkillCategory {
+    /// Scaled by `attack_speed`.
+    Attack,
+    /// Scaled by `ability_haste`.
+    Ability,
+}
+
+enum Activation {
+    /// Used whenever ready, for as long as the binding is pressed.
+    Held,
+    /// Used once per press of the binding.
+    Pressed,
+}
+
+/// State of the caster that skills can depend on.
+struct SkillContext {
+    direction: Vec2,
+    /// Number of alive summons of the caster.
+    summons: usize,
+}
+
+#[derive(Clone, Copy)]
+pub enum Binding {
+    Mouse(MouseButton),
+    Key(KeyCode),
+}
+impl Binding {
+    fn pressed(&self, mouse: &ButtonInput<MouseButton>, keyboard: &ButtonInput<KeyCode>) -> bool {
+        match *self {
+            Self::Mouse(button) => mouse.pressed(button),
+            Self::Key(key) => keyboard.pressed(key),
+        }
+    }
+    fn just_pressed(
+        &self,
+        mouse: &ButtonInput<MouseButton>,
+        keyboard: &ButtonInput<KeyCode>,
+    ) -> bool {
+        match *self {
+            Self::Mouse(button) => mouse.just_pressed(button),
+            Self::Key(key) => keyboard.just_pressed(key),
+        }
+    }
+}
+
+pub struct SkillSlot {
+    pub skill: Skill,
+    pub binding: Binding,
+    /// Applied to the projectiles of the skill, if it has any.
+    modifiers: ProjectileModifiers,
+    cooldown: Timer,
+    /// Whether the binding is currently pressed.
+    held: bool,
+}
+impl SkillSlot {
+    fn new(skill: Skill, modifiers: ProjectileModifiers, binding: Binding) -> Self {
+        let mut cooldown = Timer::from_seconds(skill.cooldown(), TimerMode::Once);
+        // Every skill is ready at the start.
+        cooldown.finish();
+        Self {
+            skill,
+            binding,
+            modifiers,
+            cooldown,
+            held: false,
+        }
+    }
+    /// Cooldown in seconds, after scaling by the `Stats` of its category.
+    pub fn effective_cooldown(&self, stats: &Stats) -> f32 {
+        self.skill.cooldown() / self.skill.cooldown_rate(stats)
+    }
+    /// Seconds until the skill is ready, after scaling by the `Stats` of its category.
+    pub fn remaining_cooldown(&self, stats: &Stats) -> f32 {
+        self.cooldown.remaining_secs() / self.skill.cooldown_rate(stats)
+    }
+}
+
+/// Slots A, B, C and D, in the order of the HUD icons.
+#[derive(Component)]
+pub struct SkillSlots(pub [SkillSlot; 4]);
+impl SkillSlots {
+    pub fn new(skills: [(Skill, ProjectileModifiers); 4]) -> Self {
+        SkillSlots(std::array::from_fn(|i| {
+            let (skill, modifiers) = skills[i];
+            SkillSlot::new(skill, modifiers, SLOT_BINDINGS[i])
+        }))
+    }
+    /// Whether the binding of any attack is pressed. Abilities like the dash do not count.
+    pub fn attack_held(&self) -> bool {
+        self.0
+            .iter()
+            .any(|slot| slot.held && slot.skill.category() == SkillCategory::Attack)
+    }
+}
+
+#[derive(Message)]
+pub struct SkillUsed {
+    pub caster: Entity,
+    pub skill: Skill,
+    /// Projectile modifiers of the slot, without those of the caster's equipment.
+    pub modifiers: ProjectileModifiers,
+}
+
+/// Movement overridden by the dash, for its duration.
+#[derive(Component)]
+pub struct Dashing {
+    pub direction: Vec2,
+    timer: Timer,
+}
+
+/// Enemy locked on by the Engineer, taking more damage from summons.
+#[derive(Component)]
+pub struct Marked {
+    timer: Timer,
+    /// Visible mark above the enemy.
+    indicator: Entity,
+}
+impl Marked {
+    pub fn amplify(&self, damage: Damage) -> Damage {
+        damage.scaled(1.0 + MARK_AMPLIFICATION)
+    }
+}
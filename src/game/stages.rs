@@ -1,10 +1,17 @@
 use super::ChangeStage;
 use super::CollisionGroup;
-use super::EnemySpawner;
 use super::Item;
 use super::MainState;
 use super::Player;
-use crate::utils;
+use super::boss::BossKind;
+use super::boss::BossRoom;
+use super::director::Director;
+use super::director::DirectorConfig;
+use super::hazard::HazardCycle;
+use super::hazard::HazardKind;
+use super::hazard::hazard;
+use super::hazard::switch;
+use super::items::item;
 
 use avian2d::prelude::*;
 use bevy::prelude::*;
@@ -44,6 +51,17 @@ pub fn stage1(
     q_stages.iter().for_each(|stage| {
         commands.entity(stage).despawn();
     });
+    let director = DirectorConfig {
+        depth: 1,
+        spawn_points: vec![
+            Vec2::new(18.0, 2.0),
+            Vec2::new(30.0, 2.0),
+            Vec2::new(-25.0, 2.0),
+            Vec2::new(-40.0, 2.0),
+            Vec2::new(1.0, 5.0),
+        ],
+        ..Default::default()
+    };
     commands
         .spawn((
             Name::new("Stage 1"),
@@ -54,26 +72,113 @@ pub fn stage1(
             Collider::rectangle(100.0, 1.0),
             CollisionLayers::new(CollisionGroup::Terrain, LayerMask::ALL),
             DespawnOnExit(MainState::Game),
+            Director::new(&director),
+            director,
         ))
         .with_children(|parent| {
+            parent.spawn(platform(Vec3::new(-4.0, 1.55, 0.0), Vec2::new(4.0, 0.5)));
+            parent.spawn(platform(Vec3::new(1.0, 3.35, 0.0), Vec2::new(3.0, 0.5)));
             parent.spawn((
-                Transform::from_translation(Vec3::new(18.0, 9.0, 0.5)),
-                EnemySpawner::default(),
+                Name::from("Door 2"),
+                Door(2),
+                Sprite::from_color(Color::BLACK, Vec2::splat(2.0)),
+                Transform::from_translation(Vec3::new(-20.0, 1.5, 0.2)),
+            ));
+            parent.spawn(item(Item::Banana, &asset_server, Vec3::new(-3.0, 5.0, 0.4)));
+            parent.spawn(item(Item::Coffee, &asset_server, Vec3::new(-6.0, 5.0, 0.4)));
+            parent.spawn(item(Item::Dice, &asset_server, Vec3::new(-9.0, 5.0, 0.4)));
+            parent.spawn(item(
+                Item::Spring,
+                &asset_server,
+                Vec3::new(-12.0, 5.0, 0.4),
+            ));
+            parent.spawn(item(
+                Item::Magnet,
+                &asset_server,
+                Vec3::new(-15.0, 5.0, 0.4),
+            ));
+            parent.spawn(hazard(
+                HazardKind::Spikes,
+                Vec3::new(8.0, 0.75, 0.3),
+                Vec2::new(2.0, 0.5),
+            ));
+            parent.spawn((
+                hazard(
+                    HazardKind::Lava,
+                    Vec3::new(14.0, 0.6, 0.3),
+                    Vec2::new(3.0, 0.2),
+                ),
+                HazardCycle::from_seconds(3.0),
+            ));
+            let floor = parent
+                .spawn(hazard(
+                    HazardKind::ElectrifiedFloor,
+                    Vec3::new(-12.0, 0.6, 0.3),
+                    Vec2::new(4.0, 0.2),
+                ))
+                .id();
+            parent.spawn(switch(vec![floor], Vec3::new(-9.0, 0.8, 0.3)));
+        });
+}
+/// Static terrain, relative to the stage.
+fn platform(position: Vec3, size: Vec2) -> impl Bundle {
+    (
+        Name::new("Platform"),
+        RigidBody::Static,
+        Transform::from_translation(position),
+        Sprite::from_color(Color::WHITE, size),
+        Collider::rectangle(size.x, size.y),
+        CollisionLayers::new(CollisionGroup::Terrain, LayerMask::ALL),
+    )
+}
+pub fn stage2(q_stages: Query<Entity, With<Stage>>, mut commands: Commands) {
+    // Make sure that there is one stage at a time.
+    q_stages.iter().for_each(|stage| {
+        commands.entity(stage).despawn();
+    });
+    commands
+        .spawn((
+            Name::new("Stage 2"),
+            Stage,
+            RigidBody::Static,
+            Transform::from_translation(Vec3::new(0.0, 0.0, 0.0)),
+            Sprite::from_color(Color::WHITE, Vec2::new(40.0, 1.0)),
+            Collider::rectangle(40.0, 1.0),
+            CollisionLayers::new(CollisionGroup::Terrain, LayerMask::ALL),
+            DespawnOnExit(MainState::Game),
+        ))
+        .with_children(|parent| {
+            parent.spawn(platform(Vec3::new(-19.5, 5.0, 0.0), Vec2::new(1.0, 10.0)));
+            parent.spawn(platform(Vec3::new(19.5, 5.0, 0.0), Vec2::new(1.0, 10.0)));
+            parent.spawn(platform(Vec3::new(6.0, 2.5, 0.0), Vec2::new(3.0, 0.5)));
+            parent.spawn(platform(Vec3::new(14.0, 2.5, 0.0), Vec2::new(3.0, 0.5)));
+            parent.spawn((
+                Name::from("Door 1"),
+                Door(1),
+                Sprite::from_color(Color::BLACK, Vec2::splat(2.0)),
+                Transform::from_translation(Vec3::new(-16.0, 1.5, 0.2)),
             ));
             parent.spawn((
-                Item::Banana,
-                utils::image_size_to_sprite(Item::Banana.image(&asset_server), Item::Banana.size()),
-                RigidBody::Dynamic,
-                Collider::rectangle(Item::Banana.size().x, Item::Banana.size().y),
-                CollisionLayers::new(CollisionGroup::Default, CollisionGroup::Terrain),
-                Mass(100.0),
-                Transform::from_translation(Vec3::new(-3.0, 5.0, 0.4)),
+                Name::new("Boss Room"),
+                BossRoom {
+                    boss: BossKind::Golem,
+                    size: Vec2::new(16.0, 10.0),
+                },
+                Transform::from_translation(Vec3::new(10.0, 5.0, 0.0)),
             ));
         });
 }
+/// Run condition of the system spawning the given stage.
+pub fn changing_to(stage: u8) -> impl FnMut(MessageReader<ChangeStage>) -> bool {
+    move |mut change_stage_messages: MessageReader<ChangeStage>| {
+        change_stage_messages
+            .read()
+            .any(|change_stage| change_stage.0 == stage)
+    }
+}
 // TODO Maybe create common logic for general interaction, regardless of door/item
 pub fn door_interaction(
-    q_door: Query<(&GlobalTransform, &Door)>,
+    q_door: Query<(&GlobalTransform, &Door), Without<Sealed>>,
     q_player: Query<&GlobalTransform, With<Player>>,
     mut change_stage_messages: MessageWriter<ChangeStage>,
 ) -> Result {
@@ -95,3 +200,7 @@ pub struct Stage;
 /// Door to a specific stage.
 #[derive(Component)]
 pub struct Door(u8);
+
+/// Door which cannot be used, e.g. while a boss is alive.
+#[derive(Component)]
+pub struct Sealed;
@@ -0,0 +1,210 @@
+use super::Equipment;
+use super::Health;
+use super::Stats;
+use super::damage::Shield;
+use super::status::StatusEffects;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+/// Recomputes `Stats` from `BaseStats` and all modifiers, whenever any of their sources changes.
+/// Current health and shield are capped by the new maxima.
+pub fn recompute(
+    mut q_entities: Query<
+        (
+            &BaseStats,
+            &mut Stats,
+            Option<&Passives>,
+            Option<&Equipment>,
+            Option<&StatusEffects>,
+            Option<&mut Health>,
+            Option<&mut Shield>,
+        ),
+        Or<(
+            Changed<BaseStats>,
+            Changed<Passives>,
+            Changed<Equipment>,
+            Changed<StatusEffects>,
+        )>,
+    >,
+) {
+    q_entities.iter_mut().for_each(
+        |(base, mut stats, passives, equipment, effects, health, shield)| {
+            let modifiers = passives
+                .into_iter()
+                .flat_map(|passives| passives.0.iter().copied())
+                .chain(
+                    equipment
+                        .into_iter()
+                        .flat_map(|equipment| equipment.stat_modifiers()),
+                )
+                .chain(
+                    effects
+                        .into_iter()
+                        .flat_map(|effects| effects.stat_modifiers()),
+                );
+            *stats = base.0.with_modifiers(modifiers);
+
+            if let Some(mut health) = health {
+                health.0 = health.0.min(stats.max_health);
+            }
+            if let Some(mut shield) = shield {
+                shield.amount = shield.amount.min(stats.shield);
+            }
+        },
+    );
+}
+
+/// Stats before any modifiers, which `Stats` are recomputed from.
+#[derive(Component)]
+pub struct BaseStats(pub Stats);
+
+/// Modifiers granted permanently by the champion.
+#[derive(Component, Default)]
+pub struct Passives(pub Vec<StatModifier>);
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Stat {
+    MaxHealth,
+    AttackSpeed,
+    AbilityHaste,
+    MovementSpeed,
+    BaseDamage,
+    CritChance,
+    CritMultiplier,
+    Shield,
+    ShieldRechargeDelay,
+    ShieldRechargeRate,
+    Armor,
+    HealthRegen,
+}
+impl Stat {
+    pub const ALL: [Stat; 12] = [
+        Self::MaxHealth,
+        Self::AttackSpeed,
+        Self::AbilityHaste,
+        Self::MovementSpeed,
+        Self::BaseDamage,
+        Self::CritChance,
+        Self::CritMultiplier,
+        Self::Shield,
+        Self::ShieldRechargeDelay,
+        Self::ShieldRechargeRate,
+        Self::Armor,
+        Self::HealthRegen,
+    ];
+}
+
+/// Change of a single stat. Every stat is computed as
+/// `(base + flat) * (1 + additive percents) * multipliers`.
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum StatModifier {
+    Flat(Stat, f32),
+    /// Fractions of the same stat add up before scaling, so two `+50%` make `+100%`.
+    AdditivePercent(Stat, f32),
+    Multiplicative(Stat, f32),
+}
+impl StatModifier {
+    pub fn stat(&self) -> Stat {
+        match *self {
+            Self::Flat(stat, _)
+            | Self::AdditivePercent(stat, _)
+            | Self::Multiplicative(stat, _) => stat,
+        }
+    }
+}
+
+impl Stats {
+    fn get_mut(&mut self, stat: Stat) -> &mut f32 {
+        match stat {
+            Stat::MaxHealth => &mut self.max_health,
+            Stat::AttackSpeed => &mut self.attack_speed,
+            Stat::AbilityHaste => &mut self.ability_haste,
+            Stat::MovementSpeed => &mut self.movement_speed,
+            Stat::BaseDamage => &mut self.base_damage,
+            Stat::CritChance => &mut self.crit_chance,
+            Stat::CritMultiplier => &mut self.crit_multiplier,
+            Stat::Shield => &mut self.shield,
+            Stat::ShieldRechargeDelay => &mut self.shield_recharge_delay,
+            Stat::ShieldRechargeRate => &mut self.shield_recharge_rate,
+            Stat::Armor => &mut self.armor,
+            Stat::HealthRegen => &mut self.health_regen,
+        }
+    }
+    /// Copy of the stats with all modifiers applied. The order of the modifiers does not matter.
+    pub fn with_modifiers(&self, modifiers: impl Iterator<Item = StatModifier>) -> Stats {
+        let mut flat = [0.0; Stat::ALL.len()];
+        let mut additive = [0.0; Stat::ALL.len()];
+        let mut multiplicative = [1.0; Stat::ALL.len()];
+        modifiers.for_each(|modifier| {
+            let i = modifier.stat() as usize;
+            match modifier {
+                StatModifier::Flat(_, value) => flat[i] += value,
+                StatModifier::AdditivePercent(_, value) => additive[i] += value,
+                StatModifier::Multiplicative(_, value) => multiplicative[i] *= value,
+            }
+        });
+
+        let mut stats = self.clone();
+        Stat::ALL.iter().for_each(|stat| {
+            let i = *stat as usize;
+            let value = stats.get_mut(*stat);
+            *value = (*value + flat[i]) * (1.0 + additive[i]) * multiplicative[i];
+        });
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base() -> Stats {
+        Stats {
+            max_health: 100.0,
+            armor: 10.0,
+            ..Default::default()
+        }
+    }
+    /// Values of all stats, in the order of `Stat::ALL`.
+    fn values(stats: &Stats) -> Vec<f32> {
+        let mut stats = stats.clone();
+        Stat::ALL.iter().map(|stat| *stats.get_mut(*stat)).collect()
+    }
+
+    #[test]
+    fn flat_then_additive_then_multiplicative() {
+        let modifiers = [
+            StatModifier::Multiplicative(Stat::MaxHealth, 2.0),
+            StatModifier::AdditivePercent(Stat::MaxHealth, 0.25),
+            StatModifier::Flat(Stat::MaxHealth, 20.0),
+            StatModifier::Multiplicative(Stat::MaxHealth, 1.5),
+            StatModifier::AdditivePercent(Stat::MaxHealth, 0.25),
+        ];
+        // (100 + 20) * (1 + 0.25 + 0.25) * 2 * 1.5
+        let stats = base().with_modifiers(modifiers.into_iter());
+        assert_eq!(stats.max_health, 540.0);
+        assert_eq!(stats.armor, 10.0);
+        let reversed = base().with_modifiers(modifiers.into_iter().rev());
+        assert_eq!(values(&reversed), values(&stats));
+    }
+    #[test]
+    fn removing_modifiers_returns_to_base() {
+        let slow = StatModifier::Multiplicative(Stat::MovementSpeed, 0.5);
+        let modifiers = [
+            StatModifier::Flat(Stat::Armor, 15.0),
+            StatModifier::AdditivePercent(Stat::MaxHealth, 0.3),
+            slow,
+        ];
+        let base = base();
+        let modified = base.with_modifiers(modifiers.into_iter());
+        assert_ne!(values(&modified), values(&base));
+
+        let without_slow = base.with_modifiers(modifiers.into_iter().filter(|m| *m != slow));
+        assert_eq!(without_slow.movement_speed, base.movement_speed);
+        assert_eq!(without_slow.armor, 25.0);
+
+        let without_any = base.with_modifiers(std::iter::empty());
+        assert_eq!(values(&without_any), values(&base));
+    }
+}
@@ -0,0 +1,208 @@
+use super::damage::Damage;
+use super::damage::DamageType;
+use super::damage::Hit;
+use super::damage::Invulnerable;
+use super::stats::Stat;
+use super::stats::StatModifier;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+/// Seconds between two damage ticks of damage over time effects.
+const TICK_INTERVAL: f32 = 0.5;
+/// Slows never stop an entity completely.
+const MIN_MOVEMENT_FACTOR: f32 = 0.1;
+
+/// Adds the inflicted statuses to their targets, according to the stacking rule of the kind.
+/// Invulnerable targets shrug them off.
+pub fn inflict(
+    mut inflict_messages: MessageReader<Inflict>,
+    mut q_targets: Query<&mut StatusEffects, Without<Invulnerable>>,
+) {
+    inflict_messages.read().for_each(|inflict| {
+        if let Ok(mut effects) = q_targets.get_mut(inflict.target) {
+            effects.add(inflict.source, inflict.status);
+        }
+    });
+}
+/// Expires effects and deals the damage of damage over time effects. Only expiring effects count
+/// as a change, so that systems reacting to `Changed<StatusEffects>` do not run every tick.
+pub fn tick(
+    time_fixed: Res<Time>,
+    mut q_affected: Query<(Entity, &mut StatusEffects)>,
+    mut hit_messages: MessageWriter<Hit>,
+) {
+    let dt = time_fixed.delta();
+    q_affected.iter_mut().for_each(|(entity, mut effects)| {
+        let ticking = effects.bypass_change_detection();
+        ticking.0.iter_mut().for_each(|effect| {
+            effect.duration.tick(dt);
+            let Some(damage_type) = effect.kind.damage_type() else {
+                return;
+            };
+            if effect.tick.tick(dt).just_finished() {
+                let amount = effect.potency * effect.stacks as f32 * TICK_INTERVAL;
+                hit_messages.write(
+                    Hit::new(effect.source, entity, Damage::new(amount, damage_type)).periodic(),
+                );
+            }
+        });
+        let count = ticking.0.len();
+        ticking.0.retain(|effect| !effect.duration.is_finished());
+        if ticking.0.len() != count {
+            effects.set_changed();
+        }
+    });
+}
+
+/// Effect over time, inflicted by `Inflict`.
+#[derive(Clone, Copy, Debug)]
+pub struct Status {
+    pub kind: StatusKind,
+    /// Seconds the effect lasts.
+    pub duration: f32,
+    /// Damage per second of damage over time, or the slowed fraction of the speed for slows.
+    pub potency: f32,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum StatusKind {
+    /// Fire damage over time.
+    Burn,
+    /// Reduced movement and attack speed.
+    Slow,
+    /// No movement nor skills.
+    Stun,
+    /// Slashing damage over time.
+    Bleed,
+}
+impl StatusKind {
+    pub const ALL: [StatusKind; 4] = [Self::Burn, Self::Slow, Self::Stun, Self::Bleed];
+
+    fn stacking(&self) -> Stacking {
+        match self {
+            Self::Burn | Self::Stun => Stacking::Refresh,
+            Self::Slow => Stacking::Intensity { max_stacks: 3 },
+            Self::Bleed => Stacking::Independent,
+        }
+    }
+    fn damage_type(&self) -> Option<DamageType> {
+        match self {
+            Self::Burn => Some(DamageType::Fire),
+            Self::Bleed => Some(DamageType::Slashing),
+            Self::Slow | Self::Stun => None,
+        }
+    }
+    /// Tint of affected enemies.
+    pub fn color(&self) -> Color {
+        match self {
+            Self::Burn => Color::srgb(1.0, 0.4, 0.0),
+            Self::Slow => Color::srgb(0.4, 0.7, 1.0),
+            Self::Stun => Color::srgb(1.0, 1.0, 0.3),
+            Self::Bleed => Color::srgb(0.6, 0.0, 0.0),
+        }
+    }
+    pub fn icon(&self) -> &'static str {
+        match self {
+            Self::Burn => "status/burn.png",
+            Self::Slow => "status/slow.png",
+            Self::Stun => "status/stun.png",
+            Self::Bleed => "status/bleed.png",
+        }
+    }
+}
+
+/// What happens when a status is inflicted on an entity already affected by the same kind.
+enum Stacking {
+    /// The duration is refreshed and the stronger potency is kept.
+    Refresh,
+    /// The duration is refreshed and the effect gets stronger, up to `max_stacks`.
+    Intensity { max_stacks: u32 },
+    /// Every application is a separate effect with its own duration.
+    Independent,
+}
+
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    /// Entity that inflicted the status, which might no longer exist.
+    source: Entity,
+    potency: f32,
+    stacks: u32,
+    duration: Timer,
+    /// Damage ticks of damage over time effects.
+    tick: Timer,
+}
+
+/// Active effects of an entity. Only entities with this component can be affected.
+#[derive(Component, Default)]
+pub struct StatusEffects(Vec<StatusEffect>);
+impl StatusEffects {
+    fn add(&mut self, source: Entity, status: Status) {
+        let existing = self.0.iter().position(|effect| effect.kind == status.kind);
+        match (status.kind.stacking(), existing) {
+            (Stacking::Refresh, Some(i)) => {
+                let effect = &mut self.0[i];
+                effect.source = source;
+                effect.potency = effect.potency.max(status.potency);
+                effect.duration = Timer::from_seconds(status.duration, TimerMode::Once);
+            }
+            (Stacking::Intensity { max_stacks }, Some(i)) => {
+                let effect = &mut self.0[i];
+                effect.source = source;
+                effect.stacks = (effect.stacks + 1).min(max_stacks);
+                effect.duration = Timer::from_seconds(status.duration, TimerMode::Once);
+            }
+            (Stacking::Independent, _) | (_, None) => self.0.push(StatusEffect {
+                kind: status.kind,
+                source,
+                potency: status.potency,
+                stacks: 1,
+                duration: Timer::from_seconds(status.duration, TimerMode::Once),
+                tick: Timer::from_seconds(TICK_INTERVAL, TimerMode::Repeating),
+            }),
+        }
+    }
+    pub fn has(&self, kind: StatusKind) -> bool {
+        self.0.iter().any(|effect| effect.kind == kind)
+    }
+    pub fn stunned(&self) -> bool {
+        self.has(StatusKind::Stun)
+    }
+    /// Multiplier of the movement speed, lowered by slows and zero while stunned.
+    pub fn movement_factor(&self) -> f32 {
+        if self.stunned() {
+            return 0.0;
+        }
+        self.0
+            .iter()
+            .filter(|effect| effect.kind == StatusKind::Slow)
+            .map(|effect| 1.0 - effect.potency * effect.stacks as f32)
+            .product::<f32>()
+            .max(MIN_MOVEMENT_FACTOR)
+    }
+    /// Modifiers of the `Stats` of the affected entity, gone as soon as the effects expire.
+    pub fn stat_modifiers(&self) -> impl Iterator<Item = StatModifier> + '_ {
+        self.0
+            .iter()
+            .filter(|effect| effect.kind == StatusKind::Slow)
+            .map(|effect| {
+                StatModifier::Multiplicative(
+                    Stat::AttackSpeed,
+                    (1.0 - effect.potency * effect.stacks as f32).max(MIN_MOVEMENT_FACTOR),
+                )
+            })
+    }
+    /// Color of the most recently inflicted effect, if there is any.
+    pub fn tint(&self) -> Option<Color> {
+        self.0.last().map(|effect| effect.kind.color())
+    }
+}
+
+/// Inflicts the `status` on the `target`, if it can be affected.
+#[derive(Message)]
+pub struct Inflict {
+    /// Entity that inflicted the status, which might no longer exist.
+    pub source: Entity,
+    pub target: Entity,
+    pub status: Status,
+}
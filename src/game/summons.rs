@@ -0,0 +1,195 @@
+use super::CollisionGroup;
+use super::Enemy;
+use super::Health;
+use super::Projectile;
+use super::RunRng;
+use super::Stats;
+use super::bullets::EnemyProjectile;
+use super::damage::ContactDamage;
+use super::damage::Damage;
+use super::damage::DamageType;
+use super::damage::Hit;
+use super::damage::Hitstun;
+use super::damage::IFrames;
+use super::skills::Skill;
+use super::skills::SkillUsed;
+use super::skills::projectile;
+use super::status::StatusEffects;
+use crate::MainState;
+
+use avian2d::prelude::*;
+use bevy::prelude::*;
+use std::cmp::Ordering;
+
+/// Maximum number of robots a single caster can have at once.
+pub const MAX_ROBOTS: usize = 3;
+const ROBOT_SIZE: Vec2 = Vec2::new(0.6, 0.6);
+const ROBOT_HEALTH: f32 = 30.0;
+/// Invulnerability after being hit, so that a crowd does not shred the robot instantly.
+const ROBOT_IFRAMES: f32 = 0.5;
+const ROBOT_SPEED: f32 = 3.0;
+/// Distance from which the robot shoots at its target.
+const ROBOT_RANGE: f32 = 6.0;
+/// Robots do not care about enemies further away.
+const ROBOT_SIGHT: f32 = 20.0;
+/// Seconds between shots, scaled by the `attack_speed` of the owner.
+const ROBOT_ATTACK_COOLDOWN: f32 = 2.0;
+const ROBOT_PROJECTILE_SPEED: f32 = 30.0;
+const ROBOT_PROJECTILE_LIFETIME: f32 = 0.5;
+const ROBOT_DAMAGE: f32 = 1.0;
+/// Impulse with which touching enemies push robots away.
+const ROBOT_CONTACT_KNOCKBACK: f32 = 1.0;
+const ROBOT_CONTACT_HITSTUN: f32 = 0.25;
+
+pub fn summon(
+    mut commands: Commands,
+    mut skill_messages: MessageReader<SkillUsed>,
+    q_casters: Query<&GlobalTransform>,
+) {
+    skill_messages
+        .read()
+        .filter(|message| message.skill == Skill::RobotSummon)
+        .for_each(|message| {
+            let Ok(transform) = q_casters.get(message.caster) else {
+                return;
+            };
+            commands.spawn((
+                Name::new("Robot"),
+                Summon {
+                    owner: message.caster,
+                },
+                Robot::default(),
+                Health(ROBOT_HEALTH),
+                StatusEffects::default(),
+                IFrames {
+                    duration: ROBOT_IFRAMES,
+                    ..default()
+                },
+                Sprite::from_color(Color::srgb(0.6, 0.6, 0.7), ROBOT_SIZE),
+                Transform::from_translation(transform.translation()),
+                RigidBody::Dynamic,
+                LockedAxes::new().lock_rotation(),
+                Collider::rectangle(ROBOT_SIZE.x, ROBOT_SIZE.y),
+                CollidingEntities::default(),
+                CollisionLayers::new(
+                    CollisionGroup::Summon,
+                    [
+                        CollisionGroup::Terrain,
+                        CollisionGroup::Enemy,
+                        CollisionGroup::Hazard,
+                        CollisionGroup::EnemyProjectile,
+                    ],
+                ),
+                DespawnOnExit(MainState::Game),
+            ));
+        });
+}
+/// Walks towards the nearest enemy and shoots it when in range.
+pub fn handle_state(
+    time_fixed: Res<Time>,
+    mut commands: Commands,
+    mut rngs: ResMut<RunRng>,
+    mut q_robots: Query<(
+        Entity,
+        &Summon,
+        &mut Robot,
+        &GlobalTransform,
+        &mut LinearVelocity,
+        &StatusEffects,
+        Has<Hitstun>,
+    )>,
+    q_enemies: Query<&GlobalTransform, With<Enemy>>,
+    q_owners: Query<&Stats>,
+) {
+    let dt = time_fixed.delta();
+    q_robots.iter_mut().for_each(
+        |(entity, summon, mut robot, transform, mut velocity, effects, hitstun)| {
+            let owner_stats = q_owners.get(summon.owner).ok();
+            let attack_speed = owner_stats.map_or(1.0, |stats| stats.attack_speed);
+            robot.attack.tick(dt.mul_f32(attack_speed));
+
+            let position = transform.translation().xy();
+            let target = q_enemies
+                .iter()
+                .map(|enemy| enemy.translation().xy() - position)
+                .filter(|offset| offset.length() < ROBOT_SIGHT)
+                .min_by(|x, y| {
+                    x.length()
+                        .partial_cmp(&y.length())
+                        .unwrap_or(Ordering::Equal)
+                });
+            let walk_speed = match target {
+                Some(offset) if offset.length() > ROBOT_RANGE => {
+                    ROBOT_SPEED * effects.movement_factor() * offset.x.signum()
+                }
+                _ => 0.0,
+            };
+            // Knockback keeps its velocity until the hitstun wears off.
+            if !hitstun {
+                velocity.x = walk_speed;
+            }
+
+            if let Some(offset) = target
+                && offset.length() <= ROBOT_RANGE
+                && robot.attack.is_finished()
+                && !effects.stunned()
+            {
+                // Robots hit as hard as their owner.
+                let damage = match owner_stats {
+                    Some(stats) => {
+                        stats.roll_damage(ROBOT_DAMAGE, DamageType::Piercing, &mut rngs.damage)
+                    }
+                    None => Damage::new(ROBOT_DAMAGE, DamageType::Piercing),
+                };
+                commands.spawn(projectile(
+                    Projectile::new(entity, damage),
+                    transform.translation(),
+                    ROBOT_PROJECTILE_SPEED * offset.normalize(),
+                    ROBOT_PROJECTILE_LIFETIME,
+                ));
+                robot.attack.reset();
+            }
+        },
+    );
+}
+/// Robots take damage from every enemy or enemy projectile touching them.
+pub fn hit(
+    q_robots: Query<(Entity, &GlobalTransform, &CollidingEntities), With<Robot>>,
+    q_enemies: Query<(&GlobalTransform, &ContactDamage), Or<(With<Enemy>, With<EnemyProjectile>)>>,
+    mut hit_messages: MessageWriter<Hit>,
+) {
+    q_robots
+        .iter()
+        .for_each(|(robot, robot_transform, colliding_entities)| {
+            colliding_entities.iter().for_each(|enemy| {
+                let Ok((enemy_transform, contact_damage)) = q_enemies.get(*enemy) else {
+                    return;
+                };
+                let away = (robot_transform.translation() - enemy_transform.translation())
+                    .xy()
+                    .normalize_or_zero();
+                hit_messages.write(
+                    Hit::new(*enemy, robot, contact_damage.0)
+                        .with_knockback(ROBOT_CONTACT_KNOCKBACK * away, ROBOT_CONTACT_HITSTUN),
+                );
+            });
+        });
+}
+
+/// Allied unit summoned by the `owner`.
+#[derive(Component)]
+pub struct Summon {
+    pub owner: Entity,
+}
+
+#[derive(Component)]
+pub struct Robot {
+    attack: Timer,
+}
+impl Default for Robot {
+    fn default() -> Self {
+        Robot {
+            attack: Timer::from_seconds(ROBOT_ATTACK_COOLDOWN, TimerMode::Once),
+        }
+    }
+}
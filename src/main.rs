@@ -30,6 +30,7 @@ fn main() {
         //.add_plugins(bevy_inspector_egui::quick::WorldInspectorPlugin::default())
         // Needs to be done after StatesPlugin (part of DefaultPlugins)
         .init_state::<MainState>() // Initial state will be the #[default]
+        .init_resource::<assets::PreloadedAssets>()
         .add_systems(Startup, startup)
         .add_plugins(splash::plugin)
         .add_plugins(menu::plugin)
@@ -1,4 +1,5 @@
 use super::MainState;
+use crate::game::SelectedChampion;
 use crate::settings::UserSettings;
 use crate::utils::ui;
 
@@ -11,7 +12,10 @@ use bevy::ui_widgets::observe;
 pub fn plugin(app: &mut App) {
     app.add_sub_state::<MenuSubState>()
         .add_systems(OnEnter(MenuSubState::Main), main_ui)
-        .add_systems(Update, handle_keyboard.run_if(in_state(MenuSubState::Main)))
+        .add_systems(
+            Update,
+            (handle_keyboard, update_champion_text).run_if(in_state(MenuSubState::Main)),
+        )
         .add_systems(Update, update_interacted_buttons_display)
         .add_systems(OnEnter(MenuSubState::Settings), settings_ui)
         .add_systems(
@@ -34,6 +38,16 @@ fn main_ui(mut commands: Commands) -> Result {
                     },
                 ),
             ));
+            parent.spawn((
+                MyButton,
+                ChampionText,
+                Text::default(),
+                observe(
+                    |_: On<Activate>, mut selected_champion: ResMut<SelectedChampion>| {
+                        selected_champion.0.cycle()
+                    },
+                ),
+            ));
             parent.spawn((
                 MyButton,
                 Text::new("Settings"),
@@ -172,6 +186,13 @@ fn update_interacted_buttons_display(
     });
 }
 
+fn update_champion_text(
+    mut q_champion: Query<&mut Text, With<ChampionText>>,
+    selected_champion: Res<SelectedChampion>,
+) -> Result {
+    q_champion.single_mut()?.0 = format!("Champion: {}", selected_champion.0);
+    Ok(())
+}
 fn update_window_mode_text(
     mut q_window_mode: Query<&mut Text, With<WindowModeText>>,
     user_settings: Res<UserSettings>,
@@ -199,6 +220,8 @@ enum MenuSubState {
 #[require(Interaction, Button, BackgroundColor)]
 struct MyButton;
 
+#[derive(Component)]
+struct ChampionText;
 #[derive(Component)]
 struct ResolutionText;
 #[derive(Component)]
@@ -1,4 +1,5 @@
 use super::MainState;
+use crate::assets::PreloadedAssets;
 
 use bevy::prelude::*;
 
@@ -21,10 +22,15 @@ fn setup(mut commands: Commands, _asset_server: Res<AssetServer>) {
 
 fn splash_update(
     time: Res<Time>,
+    asset_server: Res<AssetServer>,
+    preloaded: Res<PreloadedAssets>,
     mut next_state: ResMut<NextState<MainState>>,
     mut q_timer: Query<&mut SplashTimer>,
 ) -> Result {
-    if q_timer.single_mut()?.0.tick(time.delta()).is_finished() {
+    // Stay on the splash screen until everything needed by the menu and the game is loaded.
+    if q_timer.single_mut()?.0.tick(time.delta()).is_finished()
+        && preloaded.all_loaded(&asset_server)?
+    {
         next_state.set(MainState::Menu);
     }
     Ok(())
@@ -35,6 +35,28 @@ impl Lifetime {
         })
     }
 }
+/// Small deterministic random number generator (SplitMix64), so that a seed reproduces a run.
+pub struct Rng(u64);
+impl Rng {
+    pub fn seeded(seed: u64) -> Self {
+        Rng(seed)
+    }
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+    /// Uniformly distributed in `[0, 1)`.
+    pub fn f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+    /// True with the given probability.
+    pub fn chance(&mut self, probability: f32) -> bool {
+        self.f32() < probability
+    }
+}
 pub mod ui {
     use bevy::prelude::*;
 